@@ -2,7 +2,8 @@ extern crate bindgen;
 
 use anyhow::{Error, Result};
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
 #[cfg(not(feature = "dynamic"))]
 use std::fmt::Debug;
@@ -25,13 +26,32 @@ fn main() -> Result<()> {
         let libcziapi_src = libczi_dir.join("Src/libCZIAPI/src");
         let libcziapi_h = libcziapi_inc.join("libCZIApi.h");
 
+        check_submodule(&libczi_dir)?;
+        check_tool(
+            "cmake",
+            "install cmake (https://cmake.org) and make sure it is in PATH, or set CMAKE to its location",
+        )?;
+        #[cfg(not(feature = "dynamic"))]
+        check_tool(
+            "nm",
+            "install binutils (or the equivalent for your platform) and make sure nm is in PATH",
+        )?;
+
+        #[cfg(feature = "system-zstd")]
+        let prefer_external_zstd = "ON";
+        #[cfg(not(feature = "system-zstd"))]
+        let prefer_external_zstd = "OFF";
+
         let dst = cmake::Config::new(&libczi_dir)
             .cxxflag("-fms-extensions")
             .define("LIBCZI_BUILD_UNITTESTS", "OFF")
             .define("LIBCZI_BUILD_CZICMD", "OFF")
             .define("LIBCZI_BUILD_DYNLIB", "OFF")
             .define("LIBCZI_BUILD_PREFER_EXTERNALPACKAGE_EIGEN3", "OFF")
-            .define("LIBCZI_BUILD_PREFER_EXTERNALPACKAGE_ZSTD", "OFF")
+            .define(
+                "LIBCZI_BUILD_PREFER_EXTERNALPACKAGE_ZSTD",
+                prefer_external_zstd,
+            )
             .define("LIBCZI_BUILD_CURL_BASED_STREAM", "OFF")
             .define("LIBCZI_BUILD_PREFER_EXTERNALPACKAGE_LIBCURL", "OFF")
             .define("LIBCZI_BUILD_AZURESDK_BASED_STREAM", "OFF")
@@ -107,6 +127,10 @@ fn main() -> Result<()> {
                 "cargo::rustc-link-search=native={}",
                 dst.join("lib64").display()
             );
+            #[cfg(feature = "system-zstd")]
+            pkg_config::probe_library("libzstd")
+                .map_err(|e| Error::msg(format!("cannot find system zstd: {}", e)))?;
+            #[cfg(not(feature = "system-zstd"))]
             println!("cargo::rustc-link-lib=static=zstd");
         }
 
@@ -123,6 +147,34 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// check that the libczi submodule is checked out, cmake fails with an opaque error otherwise
+fn check_submodule(libczi_dir: &Path) -> Result<()> {
+    if !libczi_dir.join("CMakeLists.txt").exists() {
+        let message = format!(
+            "the libczi submodule in {} is not checked out, run `git submodule update --init`",
+            libczi_dir.display()
+        );
+        println!("cargo::warning={}", message);
+        return Err(Error::msg(message));
+    }
+    Ok(())
+}
+
+/// check that a tool needed for the build can be run, the environment variable named after the tool in upper case
+/// overrides its location, like the cmake crate does for cmake
+fn check_tool(tool: &str, advice: &str) -> Result<()> {
+    let program = env::var(tool.to_uppercase()).unwrap_or(tool.to_string());
+    if Command::new(&program).arg("--version").output().is_err() {
+        let message = format!(
+            "cannot run {}, which is needed to build libczi: {}",
+            program, advice
+        );
+        println!("cargo::warning={}", message);
+        return Err(Error::msg(message));
+    }
+    Ok(())
+}
+
 #[cfg(not(feature = "dynamic"))]
 #[derive(Debug)]
 struct DeMangler {
@@ -132,7 +184,25 @@ struct DeMangler {
 #[cfg(not(feature = "dynamic"))]
 impl DeMangler {
     fn new(a_file: PathBuf) -> Result<Self> {
-        let cmd = std::process::Command::new("nm").arg(&a_file).output()?;
+        if !a_file.exists() {
+            return Err(Error::msg(format!(
+                "cannot find {}, the cmake build of libCZIAPI did not produce the static library",
+                a_file.display()
+            )));
+        }
+        let program = env::var("NM").unwrap_or("nm".to_string());
+        let cmd = Command::new(&program)
+            .arg(&a_file)
+            .output()
+            .map_err(|e| Error::msg(format!("cannot run {}: {}", program, e)))?;
+        if !cmd.status.success() {
+            return Err(Error::msg(format!(
+                "{} failed on {}: {}",
+                program,
+                a_file.display(),
+                String::from_utf8_lossy(&cmd.stderr)
+            )));
+        }
         let pat = Regex::new(r"^[\da-f]*\s[A-Z]\s(.*_Z(\d+)(libCZI_.*))$")?;
         let mut map = HashMap::new();
         for line in std::str::from_utf8(&cmd.stdout)?.lines() {
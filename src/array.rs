@@ -0,0 +1,212 @@
+//! Reading pixel data into ndarray arrays, available with the feature 'ndarray'.
+use crate::functions::LockedBitmap;
+use crate::handle::CziReader;
+use crate::interop::{AccessorOptions, Coordinate, IntRect};
+use crate::misc::{Dimension, PixelType};
+use anyhow::{Error, Result, anyhow};
+use ndarray::{Array2, Array3, ArrayViewMut2, Axis};
+use std::mem;
+
+/// element type of an array holding the pixel data of a gray pixel type
+pub trait PixelElement: Copy + Default {
+    /// the pixel type whose pixels are of this type
+    const PIXEL_TYPE: PixelType;
+
+    /// read one element from the native-endian bytes of a pixel
+    fn from_ne_slice(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_pixel_element {
+    ($($t:ty: $pixel_type:ident),* $(,)?) => {
+        $(
+            impl PixelElement for $t {
+                const PIXEL_TYPE: PixelType = PixelType::$pixel_type;
+
+                fn from_ne_slice(bytes: &[u8]) -> Self {
+                    <$t>::from_ne_bytes(bytes.try_into().expect("slice must have the size of the element"))
+                }
+            }
+        )*
+    };
+}
+
+impl_pixel_element!(u8: Gray8, u16: Gray16, u32: Gray32, f32: Gray32Float, f64: Gray64Float);
+
+/// the pixel data of a bitmap as an array with the element type of its pixel type, see [LockedBitmap::to_ndarray]
+#[derive(Clone, Debug)]
+pub enum BitmapArray {
+    Gray8(Array2<u8>),
+    Gray16(Array2<u16>),
+    Gray32(Array2<u32>),
+    Gray32Float(Array2<f32>),
+    Gray64Float(Array2<f64>),
+    /// the channels are in the order in which they are stored: blue, green, red
+    Bgr24(Array3<u8>),
+    /// the channels are in the order in which they are stored: blue, green, red
+    Bgr48(Array3<u16>),
+    /// the channels are in the order in which they are stored: blue, green, red
+    Bgr96Float(Array3<f32>),
+    /// the channels are in the order in which they are stored: blue, green, red, alpha
+    Bgra32(Array3<u8>),
+}
+
+impl LockedBitmap {
+    /// Copy the pixel data into an array, respecting the stride. Gray pixel types give an array of shape
+    /// (height, width), bgr pixel types an array of shape (height, width, channels) with the channels in the order in
+    /// which they are stored. An error is returned for the complex pixel types.
+    pub fn to_ndarray(&self) -> Result<BitmapArray> {
+        Ok(match self.get_info()?.get_pixel_type()? {
+            PixelType::Gray8 => BitmapArray::Gray8(self.to_gray_array()?),
+            PixelType::Gray16 => BitmapArray::Gray16(self.to_gray_array()?),
+            PixelType::Gray32 => BitmapArray::Gray32(self.to_gray_array()?),
+            PixelType::Gray32Float => BitmapArray::Gray32Float(self.to_gray_array()?),
+            PixelType::Gray64Float => BitmapArray::Gray64Float(self.to_gray_array()?),
+            PixelType::Bgr24 => BitmapArray::Bgr24(self.to_interleaved_array(3)?),
+            PixelType::Bgr48 => BitmapArray::Bgr48(self.to_interleaved_array(3)?),
+            PixelType::Bgr96Float => BitmapArray::Bgr96Float(self.to_interleaved_array(3)?),
+            PixelType::Bgra32 => BitmapArray::Bgra32(self.to_interleaved_array(4)?),
+            pixel_type => {
+                return Err(anyhow!(
+                    "bitmap of pixel type {:?} cannot be converted into an array",
+                    pixel_type
+                ));
+            }
+        })
+    }
+
+    /// copy the pixel data into an array of shape (height, width)
+    fn to_gray_array<T: PixelElement>(&self) -> Result<Array2<T>> {
+        Ok(self.to_interleaved_array(1)?.index_axis_move(Axis(2), 0))
+    }
+
+    /// copy the pixel data into an array of shape (height, width, channels), the pixel type is not checked
+    fn to_interleaved_array<T: PixelElement>(&self, channels: usize) -> Result<Array3<T>> {
+        let info = self.get_info()?;
+        let size = mem::size_of::<T>();
+        let data = self
+            .rows()?
+            .flat_map(|row| row.chunks_exact(size).map(T::from_ne_slice))
+            .collect();
+        Ok(Array3::from_shape_vec(
+            (
+                info.get_height() as usize,
+                info.get_width() as usize,
+                channels,
+            ),
+            data,
+        )?)
+    }
+
+    /// copy the pixel data of the bitmap into an array of shape (height, width), respecting the stride
+    pub(crate) fn copy_into<T: PixelElement>(&self, mut array: ArrayViewMut2<T>) -> Result<()> {
+        let info = self.get_info()?;
+        if info.get_pixel_type()? as i32 != T::PIXEL_TYPE as i32 {
+            return Err(anyhow!(
+                "bitmap of pixel type {:?} cannot be read as {:?}",
+                info.get_pixel_type()?,
+                T::PIXEL_TYPE
+            ));
+        }
+        let (height, width) = array.dim();
+        if info.get_height() as usize != height || info.get_width() as usize != width {
+            return Err(Error::msg("bitmap and array have different shapes"));
+        }
+        let size = mem::size_of::<T>();
+        let stride = self.lock_info.get_stride() as usize;
+        if height > 0 && (height - 1) * stride + width * size > self.lock_info.data_len() {
+            return Err(Error::msg(
+                "bitmap data is smaller than its size and stride imply",
+            ));
+        }
+        let data = self.data();
+        for (y, mut row) in array.rows_mut().into_iter().enumerate() {
+            let row_data = &data[y * stride..y * stride + width * size];
+            for (element, bytes) in row.iter_mut().zip(row_data.chunks_exact(size)) {
+                *element = T::from_ne_slice(bytes);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// the channels of one time point, see [CziReader::time_series]
+#[derive(Clone, Debug)]
+pub struct Frame<T> {
+    /// the T index
+    pub t: i32,
+    /// the time stamp of this time point, if the document has time stamps
+    pub time_stamp: Option<f64>,
+    /// the pixel data, with shape (channels, height, width)
+    pub data: Array3<T>,
+}
+
+impl CziReader {
+    /// Read the region of interest at full resolution (zoom 1.0) for all channels of the plane given by base, and
+    /// stack them into an array of shape (channels, height, width). The C value of base is ignored. All channels
+    /// must have the pixel type corresponding to T, otherwise an error is returned.
+    pub fn read_region_multichannel<T: PixelElement>(
+        &self,
+        base: Coordinate,
+        roi: IntRect,
+    ) -> Result<Array3<T>> {
+        let pixel_types = self.channel_pixel_types()?;
+        for (c, pixel_type) in &pixel_types {
+            if pixel_type.clone() as i32 != T::PIXEL_TYPE as i32 {
+                return Err(anyhow!(
+                    "channel {} has pixel type {:?}, which is incompatible with {:?}",
+                    c,
+                    pixel_type,
+                    T::PIXEL_TYPE
+                ));
+            }
+        }
+        let accessor = self.create_single_channel_tile_accessor()?;
+        let mut array = Array3::default((
+            pixel_types.len(),
+            roi.get_h() as usize,
+            roi.get_w() as usize,
+        ));
+        for (mut plane, c) in array.outer_iter_mut().zip(pixel_types.keys()) {
+            let mut coordinate = base.clone();
+            coordinate.set(Dimension::C, *c);
+            self.check_region_decode_size(&coordinate, &roi, 1.0)?;
+            let bitmap = accessor
+                .get(coordinate, roi.clone(), 1.0, AccessorOptions::default())?
+                .lock()?;
+            bitmap.copy_into(plane.view_mut())?;
+        }
+        Ok(array)
+    }
+
+    /// Iterate over the time points of the plane given by base, reading the region of interest of all channels at
+    /// full resolution for each (see [CziReader::read_region_multichannel]). The T and C values of base are ignored.
+    /// Frames are read lazily, so only one frame is kept in memory at a time. If the document has no T dimension,
+    /// a single frame with T index 0 is returned.
+    pub fn time_series<T: PixelElement>(
+        &self,
+        base: Coordinate,
+        roi: IntRect,
+    ) -> Result<impl Iterator<Item = Result<Frame<T>>> + '_> {
+        let t_range = self
+            .get_statistics_simple()?
+            .get_dim_bounds()
+            .get(Dimension::T)
+            .map(|(start, size)| start..start + size);
+        let time_stamps = self.time_stamps()?.unwrap_or_default();
+        let has_t = t_range.is_some();
+        let t_range = t_range.unwrap_or(0..1);
+        let t_start = t_range.start;
+        Ok(t_range.map(move |t| {
+            let mut coordinate = base.clone();
+            if has_t {
+                coordinate.set(Dimension::T, t);
+            }
+            let index = (t - t_start) as usize;
+            Ok(Frame {
+                t,
+                time_stamp: time_stamps.get(index).copied(),
+                data: self.read_region_multichannel(coordinate, roi.clone())?,
+            })
+        }))
+    }
+}
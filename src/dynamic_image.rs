@@ -0,0 +1,112 @@
+//! Converting bitmaps into image::DynamicImage, available with the feature 'image'.
+use crate::functions::LockedBitmap;
+use crate::misc::PixelType;
+use anyhow::{Error, Result, anyhow};
+use image::{DynamicImage, ImageBuffer, ImageFormat};
+use std::path::Path;
+
+/// read the native-endian elements of a row of pixel data
+fn from_ne_bytes<T, const N: usize>(row: &[u8], from: fn([u8; N]) -> T) -> impl Iterator<Item = T> {
+    row.chunks_exact(N)
+        .map(move |bytes| from(bytes.try_into().expect("chunk must have N bytes")))
+}
+
+/// reverse the order of the first three elements of every pixel: bgr(a) to rgb(a)
+fn bgr_to_rgb<T>(data: &mut [T], channels: usize) {
+    for pixel in data.chunks_exact_mut(channels) {
+        pixel.swap(0, 2);
+    }
+}
+
+impl LockedBitmap {
+    /// Copy the pixel data into an image::DynamicImage, available with the feature 'image'. The padding up to the
+    /// stride is skipped and blue and red are swapped for the bgr pixel types. Gray32Float is converted into
+    /// Rgb32F with equal channels because image has no gray float type. An error is returned for pixel types
+    /// which image cannot represent: Gray32, Gray64Float and the complex types.
+    pub fn to_dynamic_image(&self) -> Result<DynamicImage> {
+        let info = self.get_info()?;
+        let (width, height) = (info.get_width(), info.get_height());
+        let pixel_type = info.get_pixel_type()?;
+        let rows = self.rows()?;
+        let too_small = || Error::msg("bitmap data does not fit the image buffer");
+        Ok(match pixel_type {
+            PixelType::Gray8 => DynamicImage::ImageLuma8(
+                ImageBuffer::from_raw(width, height, rows.flatten().copied().collect())
+                    .ok_or_else(too_small)?,
+            ),
+            PixelType::Gray16 => DynamicImage::ImageLuma16(
+                ImageBuffer::from_raw(
+                    width,
+                    height,
+                    rows.flat_map(|row| from_ne_bytes(row, u16::from_ne_bytes))
+                        .collect(),
+                )
+                .ok_or_else(too_small)?,
+            ),
+            PixelType::Gray32Float => DynamicImage::ImageRgb32F(
+                ImageBuffer::from_raw(
+                    width,
+                    height,
+                    rows.flat_map(|row| from_ne_bytes(row, f32::from_ne_bytes))
+                        .flat_map(|value| [value; 3])
+                        .collect(),
+                )
+                .ok_or_else(too_small)?,
+            ),
+            PixelType::Bgr24 => {
+                let mut data: Vec<u8> = rows.flatten().copied().collect();
+                bgr_to_rgb(&mut data, 3);
+                DynamicImage::ImageRgb8(
+                    ImageBuffer::from_raw(width, height, data).ok_or_else(too_small)?,
+                )
+            }
+            PixelType::Bgr48 => {
+                let mut data: Vec<u16> = rows
+                    .flat_map(|row| from_ne_bytes(row, u16::from_ne_bytes))
+                    .collect();
+                bgr_to_rgb(&mut data, 3);
+                DynamicImage::ImageRgb16(
+                    ImageBuffer::from_raw(width, height, data).ok_or_else(too_small)?,
+                )
+            }
+            PixelType::Bgr96Float => {
+                let mut data: Vec<f32> = rows
+                    .flat_map(|row| from_ne_bytes(row, f32::from_ne_bytes))
+                    .collect();
+                bgr_to_rgb(&mut data, 3);
+                DynamicImage::ImageRgb32F(
+                    ImageBuffer::from_raw(width, height, data).ok_or_else(too_small)?,
+                )
+            }
+            PixelType::Bgra32 => {
+                let mut data: Vec<u8> = rows.flatten().copied().collect();
+                bgr_to_rgb(&mut data, 4);
+                DynamicImage::ImageRgba8(
+                    ImageBuffer::from_raw(width, height, data).ok_or_else(too_small)?,
+                )
+            }
+            pixel_type => {
+                return Err(anyhow!(
+                    "bitmap of pixel type {:?} cannot be converted into an image",
+                    pixel_type
+                ));
+            }
+        })
+    }
+
+    /// Write the bitmap to path as a PNG, available with the feature 'image'. The bit depth of 16 bit pixel types is
+    /// preserved. PNG cannot store floats, so an error is returned for the float pixel types, normalize these into
+    /// an integer pixel type first.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let pixel_type = self.get_info()?.get_pixel_type()?;
+        if matches!(pixel_type, PixelType::Gray32Float | PixelType::Bgr96Float) {
+            return Err(anyhow!(
+                "bitmap of pixel type {:?} cannot be saved as PNG, normalize it into an integer pixel type first",
+                pixel_type
+            ));
+        }
+        self.to_dynamic_image()?
+            .save_with_format(path, ImageFormat::Png)?;
+        Ok(())
+    }
+}
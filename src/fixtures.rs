@@ -0,0 +1,304 @@
+//! Synthetic CZI documents for testing, available with the feature 'testing'.
+use crate::functions::InitializedCziWriter;
+use crate::handle::{CziWriter, OutputStream};
+use crate::interop::{
+    AddAttachmentInfo, AddSubBlockInfo, Coordinate, IntRect, IntSize, WriteMetadataInfo,
+};
+use crate::misc::{CompressionMode, Dimension, PixelType, WriterInitOptions};
+use anyhow::{Error, Result};
+use std::env;
+use std::mem;
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// width and height of the sub-blocks in the tiny CZI
+pub const TINY_CZI_SIZE: i32 = 32;
+/// number of channels in the tiny CZI, each channel is a single gray8 sub-block
+pub const TINY_CZI_CHANNELS: i32 = 2;
+
+const TINY_CZI_METADATA: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<ImageDocument>
+  <Metadata>
+    <Information>
+      <Image>
+        <PixelType>Gray8</PixelType>
+        <SizeX>32</SizeX>
+        <SizeY>32</SizeY>
+        <SizeC>2</SizeC>
+      </Image>
+    </Information>
+  </Metadata>
+</ImageDocument>
+"#;
+
+/// time stamps in the TimeStamps attachment of the tiny CZI
+pub const TINY_CZI_TIME_STAMPS: [f64; 2] = [0.0, 1.5];
+
+static TEMP_FILE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// a path in the temporary directory not used by any other fixture in this process, for tests which need a file
+pub fn temp_file_path() -> PathBuf {
+    env::temp_dir().join(format!(
+        "libczirw-sys-{}-{}.czi",
+        process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// the pixel value of the tiny CZI at position x, y in channel c
+pub fn tiny_czi_pixel(c: i32, x: i32, y: i32) -> u8 {
+    (x + TINY_CZI_SIZE * y + 64 * c) as u8
+}
+
+/// the content of the TimeStamps attachment of the tiny CZI: the size in bytes and the number of time stamps as i32,
+/// followed by the time stamps as f64
+pub fn tiny_czi_time_stamps_attachment() -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend((8 + 8 * TINY_CZI_TIME_STAMPS.len() as i32).to_le_bytes());
+    data.extend((TINY_CZI_TIME_STAMPS.len() as i32).to_le_bytes());
+    for time_stamp in TINY_CZI_TIME_STAMPS {
+        data.extend(time_stamp.to_le_bytes());
+    }
+    data
+}
+
+/// add an uncompressed sub-block at coordinate and pyramid layer m_index, covering the logical rectangle with
+/// physical_size pixels
+fn add_sub_block(
+    writer: &InitializedCziWriter,
+    coordinate: Coordinate,
+    m_index: Option<i32>,
+    logical: IntRect,
+    physical_size: IntSize,
+    pixel_type: PixelType,
+    data: &[u8],
+) -> Result<()> {
+    let mut add_sub_block_info = AddSubBlockInfo::new(
+        coordinate,
+        m_index,
+        logical.get_x(),
+        logical.get_y(),
+        logical.get_w(),
+        logical.get_h(),
+        physical_size.get_w(),
+        physical_size.get_h(),
+        pixel_type,
+        CompressionMode::UnCompressed as i32,
+        data,
+        &[],
+        &[],
+    );
+    add_sub_block_info.set_stride(data.len() as u32 / physical_size.get_h() as u32);
+    writer.add_sub_block(add_sub_block_info)
+}
+
+/// add an uncompressed sub-block of TINY_CZI_SIZE x TINY_CZI_SIZE pixels for channel c
+fn add_channel(
+    writer: &InitializedCziWriter,
+    c: i32,
+    pixel_type: PixelType,
+    data: &[u8],
+) -> Result<()> {
+    let mut coordinate = Coordinate::new(0, [0; 9]);
+    coordinate.set(Dimension::C, c);
+    add_sub_block(
+        writer,
+        coordinate,
+        None,
+        IntRect::new(0, 0, TINY_CZI_SIZE, TINY_CZI_SIZE),
+        IntSize::new(TINY_CZI_SIZE, TINY_CZI_SIZE),
+        pixel_type,
+        data,
+    )
+}
+
+/// The bytes of the CZI written by write to an in-memory output stream.
+pub fn write_in_memory(write: impl FnOnce(&OutputStream) -> Result<()>) -> Result<Vec<u8>> {
+    let (output_stream, data) = OutputStream::create_in_memory()?;
+    write(&output_stream)?;
+    drop(output_stream);
+    let mut data = data.lock().map_err(|_| Error::msg("data is poisoned"))?;
+    Ok(mem::take(&mut *data))
+}
+
+/// add an attachment with the content file type and name, which must fit in 8 and 80 bytes
+fn add_attachment(
+    writer: &InitializedCziWriter,
+    guid: [u8; 16],
+    content_file_type: &str,
+    name: &str,
+    data: &[u8],
+) -> Result<()> {
+    let mut content_file_type_bytes = [0; 8];
+    content_file_type_bytes[..content_file_type.len()]
+        .copy_from_slice(content_file_type.as_bytes());
+    let mut name_bytes = [0; 80];
+    name_bytes[..name.len()].copy_from_slice(name.as_bytes());
+    writer.add_attachement(AddAttachmentInfo::new(
+        guid,
+        content_file_type_bytes,
+        name_bytes,
+        data,
+    ))
+}
+
+/// Write the tiny CZI (see [tiny_czi]) to an output stream, initializing the writer with options.
+pub fn write_tiny_czi_to(output_stream: &OutputStream, options: &WriterInitOptions) -> Result<()> {
+    let writer = CziWriter::create("{}")?.init_with_options(output_stream, options)?;
+    for c in 0..TINY_CZI_CHANNELS {
+        let data = (0..TINY_CZI_SIZE)
+            .flat_map(|y| (0..TINY_CZI_SIZE).map(move |x| tiny_czi_pixel(c, x, y)))
+            .collect::<Vec<_>>();
+        add_channel(&writer, c, PixelType::Gray8, &data)?;
+    }
+    add_attachment(
+        &writer,
+        *b"libczirw-sys-tst",
+        "CZTIMS",
+        "TimeStamps",
+        &tiny_czi_time_stamps_attachment(),
+    )?;
+    writer.write_metadata(WriteMetadataInfo::new(TINY_CZI_METADATA.as_bytes()))?;
+    writer.close()
+}
+
+/// A minimal valid CZI as bytes: one uncompressed gray8 sub-block of 32x32 pixels for each of two channels, with
+/// pixel values given by [tiny_czi_pixel], a TimeStamps attachment (see [tiny_czi_time_stamps_attachment]) and a
+/// small XML metadata segment.
+pub fn tiny_czi() -> Result<Vec<u8>> {
+    write_in_memory(|output_stream| write_tiny_czi_to(output_stream, &WriterInitOptions::default()))
+}
+
+/// the content of the Thumbnail attachment of the thumbnail CZI, the start and end markers of a JPEG file
+pub const THUMBNAIL_CZI_THUMBNAIL: [u8; 4] = [0xff, 0xd8, 0xff, 0xd9];
+
+/// A CZI with attachments as written by ZEN as bytes: one uncompressed gray8 sub-block of 32x32 pixels in
+/// channel 0, a Thumbnail attachment of content file type JPG (see [THUMBNAIL_CZI_THUMBNAIL]) and a Label
+/// attachment of content file type CZI, holding a tiny CZI (see [tiny_czi]).
+pub fn thumbnail_czi() -> Result<Vec<u8>> {
+    let label = tiny_czi()?;
+    write_in_memory(|output_stream| {
+        let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+        add_channel(
+            &writer,
+            0,
+            PixelType::Gray8,
+            &vec![0; (TINY_CZI_SIZE * TINY_CZI_SIZE) as usize],
+        )?;
+        add_attachment(
+            &writer,
+            *b"libczirw-sys-thb",
+            "JPG",
+            "Thumbnail",
+            &THUMBNAIL_CZI_THUMBNAIL,
+        )?;
+        add_attachment(&writer, *b"libczirw-sys-lbl", "CZI", "Label", &label)?;
+        writer.close()
+    })
+}
+
+/// blue, green and red value of the pixels of the bgr24 channel in the mixed pixel type CZI
+pub const MIXED_PIXEL_TYPE_CZI_BGR: [u8; 3] = [1, 2, 3];
+
+/// A CZI with channels of different pixel types as bytes: channel 0 is a gray16 and channel 1 a bgr24
+/// sub-block of 32x32 pixels, both uncompressed. Pixel i (counting row by row) of the gray16 channel has the value
+/// 64 * i, all pixels of the bgr24 channel are MIXED_PIXEL_TYPE_CZI_BGR.
+pub fn mixed_pixel_type_czi() -> Result<Vec<u8>> {
+    write_in_memory(|output_stream| {
+        let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+        let n = (TINY_CZI_SIZE * TINY_CZI_SIZE) as usize;
+        let data = (0..n)
+            .flat_map(|i| (64 * i as u16).to_le_bytes())
+            .collect::<Vec<_>>();
+        add_channel(&writer, 0, PixelType::Gray16, &data)?;
+        add_channel(
+            &writer,
+            1,
+            PixelType::Bgr24,
+            &MIXED_PIXEL_TYPE_CZI_BGR.repeat(n),
+        )?;
+        writer.close()
+    })
+}
+
+/// A CZI with a pyramid as bytes: scene 0, channel 0 has a gray8 sub-block of 32x32 pixels on layer 0 and one
+/// of 16x16 pixels covering the same logical area on layer 1 (minification factor 2).
+pub fn pyramid_czi() -> Result<Vec<u8>> {
+    write_in_memory(|output_stream| {
+        let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+        for (m_index, physical_size) in [TINY_CZI_SIZE, TINY_CZI_SIZE / 2].into_iter().enumerate() {
+            let mut coordinate = Coordinate::new(0, [0; 9]);
+            coordinate.set(Dimension::C, 0);
+            coordinate.set(Dimension::S, 0);
+            let data = vec![m_index as u8; (physical_size * physical_size) as usize];
+            add_sub_block(
+                &writer,
+                coordinate,
+                Some(m_index as i32),
+                IntRect::new(0, 0, TINY_CZI_SIZE, TINY_CZI_SIZE),
+                IntSize::new(physical_size, physical_size),
+                PixelType::Gray8,
+                &data,
+            )?;
+        }
+        writer.close()
+    })
+}
+
+/// number of scenes in the multi-scene CZI
+pub const MULTI_SCENE_CZI_SCENES: i32 = 3;
+
+/// A CZI with MULTI_SCENE_CZI_SCENES scenes as bytes: scene s has one uncompressed gray8 sub-block of 32x32
+/// pixels in channel 0 at x = 64 * s, with all pixels equal to s.
+pub fn multi_scene_czi() -> Result<Vec<u8>> {
+    write_in_memory(|output_stream| {
+        let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+        for s in 0..MULTI_SCENE_CZI_SCENES {
+            let mut coordinate = Coordinate::new(0, [0; 9]);
+            coordinate.set(Dimension::C, 0);
+            coordinate.set(Dimension::S, s);
+            let data = vec![s as u8; (TINY_CZI_SIZE * TINY_CZI_SIZE) as usize];
+            add_sub_block(
+                &writer,
+                coordinate,
+                Some(0),
+                IntRect::new(2 * TINY_CZI_SIZE * s, 0, TINY_CZI_SIZE, TINY_CZI_SIZE),
+                IntSize::new(TINY_CZI_SIZE, TINY_CZI_SIZE),
+                PixelType::Gray8,
+                &data,
+            )?;
+        }
+        writer.close()
+    })
+}
+
+/// A CZI with size_z x size_c x size_t planes as bytes: each plane is one uncompressed gray8 sub-block of
+/// TINY_CZI_SIZE x TINY_CZI_SIZE pixels, with all pixels equal to z + size_z * (c + size_c * t).
+pub fn stack_czi(size_z: i32, size_c: i32, size_t: i32) -> Result<Vec<u8>> {
+    write_in_memory(|output_stream| {
+        let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+        for t in 0..size_t {
+            for c in 0..size_c {
+                for z in 0..size_z {
+                    let mut coordinate = Coordinate::new(0, [0; 9]);
+                    coordinate.set(Dimension::Z, z);
+                    coordinate.set(Dimension::C, c);
+                    coordinate.set(Dimension::T, t);
+                    let value = (z + size_z * (c + size_c * t)) as u8;
+                    let data = vec![value; (TINY_CZI_SIZE * TINY_CZI_SIZE) as usize];
+                    add_sub_block(
+                        &writer,
+                        coordinate,
+                        None,
+                        IntRect::new(0, 0, TINY_CZI_SIZE, TINY_CZI_SIZE),
+                        IntSize::new(TINY_CZI_SIZE, TINY_CZI_SIZE),
+                        PixelType::Gray8,
+                        &data,
+                    )?;
+                }
+            }
+        }
+        writer.close()
+    })
+}
@@ -2,10 +2,17 @@ use crate::handle::*;
 use crate::interop::*;
 use crate::misc::*;
 use crate::sys::*;
-use anyhow::{Error, Result};
+use anyhow::{Error, Result, anyhow};
+use std::cell::Cell;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::{CStr, CString, c_char, c_int, c_ulong, c_void};
-use std::mem::{ManuallyDrop, MaybeUninit};
-use std::ops::Deref;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ops::{Deref, Range};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::{ptr, slice};
 
 /// Release the memory - this function is to be used for freeing memory allocated by the libCZIApi-library
 ///  (and returned to the caller).
@@ -57,6 +64,67 @@ impl LibCZIBuildInformation {
     }
 }
 
+/// Check that libCZIApi is functional, for instance at the start of a long-running program, so that link or ABI
+/// problems surface early instead of on the first real request. This gets the version and build information of
+/// libCZIApi and, with the feature 'testing', also writes a tiny CZI (see [crate::fixtures::tiny_czi]) to memory,
+/// reads it back and checks its statistics and the size of a decoded bitmap.
+pub fn self_test() -> Result<()> {
+    LibCZIVersionInfo::get_lib_czi_version_info()?;
+    let build_information = LibCZIBuildInformation::get()?;
+    build_information.get_compiler_information()?;
+    build_information.get_repository_url()?;
+    build_information.get_repository_branch()?;
+    build_information.get_repository_tag()?;
+    #[cfg(any(test, feature = "testing"))]
+    self_test_round_trip()?;
+    Ok(())
+}
+
+/// A single line describing the version and build of libCZIApi, to paste into bug reports, in the form:
+/// libCZI major.minor.patch (compiler: ..., repository: ..., branch: ..., tag: ...).
+/// The tweak version is left out because libCZI does not use it, fields of the build information that cannot be read
+/// are reported as unknown.
+pub fn version_report() -> Result<String> {
+    let version = LibCZIVersionInfo::get_lib_czi_version_info()?;
+    let build_information = LibCZIBuildInformation::get();
+    let field = |get: fn(&LibCZIBuildInformation) -> Result<&str>| {
+        build_information
+            .as_ref()
+            .ok()
+            .and_then(|build_information| get(build_information).ok())
+            .filter(|value| !value.is_empty())
+            .unwrap_or("unknown")
+            .to_string()
+    };
+    Ok(format!(
+        "libCZI {}.{}.{} (compiler: {}, repository: {}, branch: {}, tag: {})",
+        version.get_major(),
+        version.get_minor(),
+        version.get_patch(),
+        field(LibCZIBuildInformation::get_compiler_information),
+        field(LibCZIBuildInformation::get_repository_url),
+        field(LibCZIBuildInformation::get_repository_branch),
+        field(LibCZIBuildInformation::get_repository_tag),
+    ))
+}
+
+#[cfg(any(test, feature = "testing"))]
+fn self_test_round_trip() -> Result<()> {
+    use crate::fixtures::{TINY_CZI_CHANNELS, TINY_CZI_SIZE, tiny_czi};
+
+    let reader = CziReader::open_from_memory(&tiny_czi()?)?;
+    if reader.sub_block_count()? != TINY_CZI_CHANNELS {
+        return Err(Error::msg("self test: wrong number of sub-blocks"));
+    }
+    let bitmap_info = reader.read_sub_block(0)?.create_bitmap()?.get_info()?;
+    if bitmap_info.get_width() != TINY_CZI_SIZE as u32
+        || bitmap_info.get_height() != TINY_CZI_SIZE as u32
+    {
+        return Err(Error::msg("self test: wrong bitmap size"));
+    }
+    Ok(())
+}
+
 impl CziReader {
     /// Create a new CZI-reader object.
     ///
@@ -82,9 +150,120 @@ impl CziReader {
         Ok(())
     }
 
+    /// Create a reader and open the CZI-document at path, creating a stream for the file (see
+    /// [InputStream::create_from_file_utf8]). The reader holds its own reference to the stream, so the stream object
+    /// created here can be released when this function returns; the file stays open until the reader is released.
+    /// An error is returned if the file is part of a multi-file document, see [CziReader::has_external_data].
+    pub fn open_from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(anyhow!("file {} does not exist", path.display()));
+        }
+        let mut file_header = Vec::with_capacity(FILE_HEADER_LEN);
+        std::fs::File::open(path)?
+            .take(FILE_HEADER_LEN as u64)
+            .read_to_end(&mut file_header)?;
+        let reader = Self::create()?;
+        reader.set_file_header(file_header)?;
+        let stream = InputStream::create_from_file_utf8(
+            path.to_str().ok_or(Error::msg("cannot into str"))?,
+        )?;
+        reader.open(ReaderOpenInfo::new(&stream))?;
+        Ok(reader)
+    }
+
+    /// Create a reader and open a CZI-document held in memory. The data is copied, so it does not need to outlive the
+    /// reader. An error is returned if the data is part of a multi-file document, see
+    /// [CziReader::has_external_data].
+    pub fn open_from_memory(data: &[u8]) -> Result<Self> {
+        let reader = Self::create()?;
+        reader.set_file_header(data[..data.len().min(FILE_HEADER_LEN)].to_vec())?;
+        let stream = InputStream::create_from_memory(Arc::from(data))?;
+        reader.open(ReaderOpenInfo::new(&stream))?;
+        Ok(reader)
+    }
+
+    /// Limit the size in bytes of data decoded by this reader, to guard against corrupt or malicious files claiming
+    /// enormous bitmap sizes. Sub-blocks read after this is set return an error from [SubBlock::create_bitmap] instead
+    /// of decoding if their decompressed size exceeds the limit, see [SubBlock::create_bitmap_limited]. The functions
+    /// rendering a region with the tile accessor ([CziReader::read_crop], [CziReader::scene_pyramid],
+    /// [CziReader::scenes_with_bitmaps] and, with the feature 'ndarray', read_region_multichannel and time_series)
+    /// also check the sub-blocks in the region and the rendered bitmap against the limit. None removes the limit.
+    pub fn set_max_decode_bytes(&mut self, max_decode_bytes: Option<usize>) {
+        self.max_decode_bytes = max_decode_bytes;
+    }
+
+    /// The limit on the size in bytes of decoded data, see [CziReader::set_max_decode_bytes].
+    pub fn max_decode_bytes(&self) -> Option<usize> {
+        self.max_decode_bytes
+    }
+
+    /// Check a region about to be rendered by the tile accessor against the limit set by
+    /// [CziReader::set_max_decode_bytes]: every sub-block matching coordinate and intersecting roi, as well as the
+    /// rendered bitmap of roi at zoom, must not exceed it.
+    pub(crate) fn check_region_decode_size(
+        &self,
+        coordinate: &Coordinate,
+        roi: &IntRect,
+        zoom: f32,
+    ) -> Result<()> {
+        let Some(max_bytes) = self.max_decode_bytes else {
+            return Ok(());
+        };
+        let mut bytes_per_pixel = 0;
+        for info in self.iter_sub_block_infos() {
+            let info = info?;
+            if coordinate.matches(&info.get_coordinate()) && info.get_logical_rect().intersects(roi)
+            {
+                check_decode_size(info.decompressed_size(), max_bytes)?;
+                bytes_per_pixel = bytes_per_pixel.max(info.get_pixel_type()?.bytes_per_pixel());
+            }
+        }
+        let width = (roi.get_w().max(0) as f64 * zoom as f64).ceil();
+        let height = (roi.get_h().max(0) as f64 * zoom as f64).ceil();
+        let bitmap_size = width * height * bytes_per_pixel as f64;
+        if bitmap_size > max_bytes as f64 {
+            return Err(anyhow!(
+                "rendered bitmap size of {} bytes exceeds the limit of {} bytes",
+                bitmap_size as u64,
+                max_bytes
+            ));
+        }
+        Ok(())
+    }
+
+    /// remember the start of the file the reader is opened with, refusing multi-file documents before libCZI reads
+    /// them
+    fn set_file_header(&self, file_header: Vec<u8>) -> Result<()> {
+        if is_multi_file(&file_header) {
+            return Err(Error::msg(
+                "the document is part of a multi-file CZI, which libCZI does not support",
+            ));
+        }
+        self.file_header
+            .set(file_header)
+            .map_err(|_| Error::msg("the reader is already opened"))
+    }
+
+    /// Whether the document is part of a multi-file CZI, whose sub-blocks are stored in additional files (see
+    /// [crate::is_multi_file]). libCZI does not support these, so [CziReader::open_from_path] and
+    /// [CziReader::open_from_memory] refuse them and this returns false for a reader they opened. The start of the
+    /// file is only known to readers opened by these functions, for a reader opened with [CziReader::open] an error
+    /// is returned.
+    pub fn has_external_data(&self) -> Result<bool> {
+        let file_header = self.file_header.get().ok_or(Error::msg(
+            "the file header is only known to readers opened by open_from_path or open_from_memory",
+        ))?;
+        Ok(is_multi_file(file_header))
+    }
+
     /// Get information about the file-header of the CZI document. The information is put into the 'file_header_info_interop' structure.
     ///  This file_header_info_interop structure contains the GUID of the CZI document and the version levels of CZI.
     ///
+    ///  Note: CZI defines a second GUID, the \"PrimaryFileGuid\", and a file-part number, which are used for multi-file
+    ///  containers where sub-block data is stored in additional files. libCZI does not support multi-file containers and
+    ///  the C API exposes neither field, use [CziReader::has_external_data] or [crate::is_multi_file] to detect them.
+    ///
     ///  \\param          reader_object               The reader object.
     ///  \\param \[out\]    file_header_info_interop    If successful, the retrieved information is put here.
     ///
@@ -111,7 +290,9 @@ impl CziReader {
         LibCZIApiError::try_from(unsafe {
             libCZI_ReaderReadSubBlock(**self, index as c_int, ptr)
         })?;
-        Ok(unsafe { SubBlock::assume_init(sub_block) })
+        let mut sub_block = unsafe { SubBlock::assume_init(sub_block) };
+        sub_block.max_decode_bytes = self.max_decode_bytes;
+        Ok(sub_block)
     }
 
     /// Get statistics about the sub-blocks in the CZI-document. This function provides a simple version of the statistics, the
@@ -128,6 +309,12 @@ impl CziReader {
         Ok(unsafe { SubBlockStatistics::assume_init(statistics) })
     }
 
+    /// The number of sub-blocks in the CZI-document. libCZI determines the statistics once when the
+    /// document is opened, so this is cheap (O(1)): it only copies the precomputed simple statistics.
+    pub fn sub_block_count(&self) -> Result<i32> {
+        Ok(self.get_statistics_simple()?.get_sub_block_count())
+    }
+
     /// Get extended statistics about the sub-blocks in the CZI-document. This function provides a more detailed version of the statistics,
     ///  including the per-scene statistics. Note that the statistics is of variable size, and the semantic is as follows:
     ///  - On input, the argument 'number_of_per_channel_bounding_boxes' must point to an integer which describes the size of the argument 'statistics'.
@@ -152,19 +339,71 @@ impl CziReader {
         &self,
         number_of_per_channel_bounding_boxes: i32,
     ) -> Result<(SubBlockStatisticsEx, i32)> {
-        let mut statistics = MaybeUninit::uninit();
-        let ptr = statistics.as_mut_ptr();
-        let number_of_per_channel_bounding_boxes =
-            Box::into_raw(Box::new(number_of_per_channel_bounding_boxes));
+        self.statistics_ex_with_capacity(number_of_per_channel_bounding_boxes)
+    }
+
+    /// The extended statistics with the bounding boxes of all scenes, see [CziReader::get_statistics_ex]. The number of
+    /// scenes is queried first, and the statistics are queried again with a larger buffer until all per-scene
+    /// bounding boxes are retrieved.
+    pub fn get_statistics_ex_all(&self) -> Result<SubBlockStatisticsEx> {
+        self.statistics_ex_all_with_capacity(0)
+    }
+
+    /// See [CziReader::get_statistics_ex_all], starting with a buffer for capacity per-scene bounding boxes.
+    pub(crate) fn statistics_ex_all_with_capacity(
+        &self,
+        mut capacity: i32,
+    ) -> Result<SubBlockStatisticsEx> {
+        loop {
+            let (statistics, available) = self.statistics_ex_with_capacity(capacity)?;
+            if available <= capacity {
+                return Ok(statistics);
+            }
+            capacity = available;
+        }
+    }
+
+    /// The extended statistics in a buffer with room for capacity per-scene bounding boxes, and the number of
+    /// per-scene bounding boxes available.
+    fn statistics_ex_with_capacity(&self, capacity: i32) -> Result<(SubBlockStatisticsEx, i32)> {
+        let capacity = capacity.max(0);
+        let size = mem::size_of::<SubBlockStatisticsInteropEx>()
+            + capacity as usize * mem::size_of::<BoundingBoxesInterop>();
+        // u32 has the alignment of SubBlockStatisticsInteropEx
+        let mut buffer = vec![0u32; size.div_ceil(mem::size_of::<u32>())];
+        let statistics = buffer.as_mut_ptr() as *mut SubBlockStatisticsInteropEx;
+        let mut available = capacity;
         LibCZIApiError::try_from(unsafe {
-            libCZI_ReaderGetStatisticsEx(**self, ptr, number_of_per_channel_bounding_boxes)
+            libCZI_ReaderGetStatisticsEx(**self, statistics, &mut available)
         })?;
-        Ok(unsafe {
-            (
-                SubBlockStatisticsEx::assume_init(statistics),
-                *Box::from_raw(number_of_per_channel_bounding_boxes),
-            )
-        })
+        let header = unsafe { ptr::read(statistics) };
+        let count = header
+            .number_of_per_scenes_bounding_boxes
+            .clamp(0, capacity) as usize;
+        let bounding_boxes = unsafe {
+            slice::from_raw_parts((*statistics).per_scenes_bounding_boxes.as_ptr(), count)
+        }
+        .iter()
+        .map(|bounding_boxes| BoundingBoxes(*bounding_boxes))
+        .collect();
+        Ok((SubBlockStatisticsEx(header, bounding_boxes), available))
+    }
+
+    /// The sorted indices of the scenes which have sub-blocks. A document without an S dimension is treated as a
+    /// single scene 0.
+    pub fn get_scene_indices(&self) -> Result<Vec<i32>> {
+        let mut scenes = self
+            .get_statistics_ex_all()?
+            .get_per_scenes_bounding_boxes()
+            .iter()
+            .map(|bounding_boxes| bounding_boxes.get_scene_index())
+            .collect::<Vec<_>>();
+        scenes.sort();
+        scenes.dedup();
+        if scenes.is_empty() && self.sub_block_count()? > 0 {
+            scenes.push(0);
+        }
+        Ok(scenes)
     }
 
     /// Get \"pyramid-statistics\" about the CZI-document. This function provides a JSON-formatted string which contains information about the pyramid.
@@ -217,6 +456,25 @@ impl CziReader {
         Ok(unsafe { MetadataSegment::assume_init(metadata_segment) })
     }
 
+    /// The range of indices of each dimension in the document, as inclusive (first, last) pairs. Dimensions not used
+    /// in the document are not in the map.
+    pub fn get_all_dimension_ranges(&self) -> Result<HashMap<Dimension, (i32, i32)>> {
+        Ok(self
+            .get_statistics_simple()?
+            .get_dim_bounds()
+            .iter()
+            .map(|(dimension, start, size)| (dimension, (start, start + size - 1)))
+            .collect())
+    }
+
+    /// The XML metadata of the CZI-document as a string, see [CziReader::get_metadata_segment] and
+    /// [MetadataSegment::get_metadata_as_xml].
+    pub fn get_metadata_xml_string(&self) -> Result<String> {
+        self.get_metadata_segment()?
+            .get_metadata_as_xml()?
+            .get_data()
+    }
+
     /// Get the number of attachments available.
     ///
     /// \\param          reader_object           The reader object.
@@ -272,6 +530,40 @@ impl CziReader {
         Ok(())
     }
 
+    /// The pyramid statistics (see [CziReader::get_pyramid_statistics]) parsed into a [PyramidStatistics].
+    pub fn pyramid_statistics(&self) -> Result<PyramidStatistics> {
+        self.get_pyramid_statistics()?.parse()
+    }
+
+    /// Whether any scene has sub-blocks on a pyramid layer other than layer 0, see [CziReader::pyramid_statistics].
+    pub fn has_pyramid(&self) -> Result<bool> {
+        Ok(self
+            .pyramid_statistics()?
+            .scene_pyramid_statistics
+            .values()
+            .flatten()
+            .any(|layer| {
+                !layer.layer_info.is_not_identified() && layer.layer_info.pyramid_layer_no > 0
+            }))
+    }
+
+    /// The number of distinct pyramid layers with sub-blocks in the scene, including layer 0. Sub-blocks which could
+    /// not be identified as part of a pyramid layer are not counted. 0 if the scene is not in the pyramid statistics.
+    pub fn pyramid_layer_count(&self, scene_index: i32) -> Result<usize> {
+        let mut layers = self
+            .pyramid_statistics()?
+            .scene_pyramid_statistics
+            .remove(&scene_index)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|layer| !layer.layer_info.is_not_identified())
+            .map(|layer| layer.layer_info.pyramid_layer_no)
+            .collect::<Vec<_>>();
+        layers.sort();
+        layers.dedup();
+        Ok(layers.len())
+    }
+
     /// Get information about the sub-block with the specified index. The information is put into the 'sub_block_info_interop' structure.
     /// If the index is not valid, then the function returns 'LibCZIApi_ErrorCode_IndexOutOfRange'.
     ///
@@ -287,6 +579,257 @@ impl CziReader {
         Ok(unsafe { SubBlockInfo::assume_init(sub_block_info) })
     }
 
+    /// Whether any sub-block in the document matches the coordinate, i.e. has the same value for every dimension
+    /// valid in the coordinate. Dimensions not valid in the coordinate are ignored.
+    pub fn contains(&self, coordinate: &Coordinate) -> Result<bool> {
+        for index in 0..self.sub_block_count()? {
+            if coordinate.matches(
+                &self
+                    .try_get_sub_block_info_for_index(index)?
+                    .get_coordinate(),
+            ) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The index of the sub-block matching the coordinate (see [Coordinate::matches]) whose logical rectangle contains
+    /// the logical point x, y. Sub-blocks on pyramid-layer 0 are preferred, otherwise the one with the smallest
+    /// downsample factor is returned. None if no sub-block covers the point.
+    pub fn sub_block_at_point(
+        &self,
+        coordinate: &Coordinate,
+        x: i32,
+        y: i32,
+    ) -> Result<Option<i32>> {
+        let mut best: Option<(i32, f64)> = None;
+        for index in 0..self.sub_block_count()? {
+            let info = self.try_get_sub_block_info_for_index(index)?;
+            if coordinate.matches(&info.get_coordinate()) && info.get_logical_rect().contains(x, y)
+            {
+                if info.is_full_resolution() {
+                    return Ok(Some(index));
+                }
+                if let Some(downsample_factor) = info.downsample_factor() {
+                    if best.is_none_or(|(_, best_factor)| downsample_factor < best_factor) {
+                        best = Some((index, downsample_factor));
+                    }
+                }
+            }
+        }
+        Ok(best.map(|(index, _)| index))
+    }
+
+    /// The information of the sub-blocks whose logical rectangle intersects rect, on all pyramid layers. If scene is
+    /// given, only sub-blocks in that scene are returned, sub-blocks without an S coordinate are taken to be in scene 0.
+    pub fn find_sub_block_infos_in_rect(
+        &self,
+        scene: Option<i32>,
+        rect: &IntRect,
+    ) -> Result<Vec<SubBlockInfo>> {
+        let mut infos = Vec::new();
+        for info in self.iter_sub_block_infos() {
+            let info = info?;
+            if scene.is_none_or(|s| info.get_coordinate().get(Dimension::S).unwrap_or(0) == s)
+                && info.get_logical_rect().intersects(rect)
+            {
+                infos.push(info);
+            }
+        }
+        Ok(infos)
+    }
+
+    /// The pixel type of each channel, determined from the first sub-block found for each channel. Sub-blocks without
+    /// a C coordinate are taken to be in channel 0.
+    pub fn channel_pixel_types(&self) -> Result<BTreeMap<i32, PixelType>> {
+        let mut pixel_types = BTreeMap::new();
+        for index in 0..self.sub_block_count()? {
+            let info = self.try_get_sub_block_info_for_index(index)?;
+            let c = info.get_coordinate().get(Dimension::C).unwrap_or(0);
+            if let Entry::Vacant(entry) = pixel_types.entry(c) {
+                entry.insert(info.get_pixel_type()?);
+            }
+        }
+        Ok(pixel_types)
+    }
+
+    /// Read the region of interest (in logical coordinates) of the plane given by coordinate at full resolution.
+    /// libCZI cannot decode part of a sub-block, so this uses the single-channel tile accessor at zoom 1.0, which
+    /// decodes only the sub-blocks intersecting the region and crops them. Parts of the region not covered by any
+    /// sub-block are black.
+    pub fn read_crop(&self, coordinate: Coordinate, roi: IntRect) -> Result<Bitmap> {
+        self.check_region_decode_size(&coordinate, &roi, 1.0)?;
+        self.create_single_channel_tile_accessor()?.get(
+            coordinate,
+            roi,
+            1.0,
+            AccessorOptions::default(),
+        )
+    }
+
+    /// Render every pyramid layer of a channel in a scene, from the finest (zoom 1.0) to the coarsest layer, as
+    /// (zoom, bitmap) pairs. Each bitmap covers the bounding box of the layer-0 sub-blocks of the scene, so together
+    /// they take about 1 / (1 - 1 / minification_factor²) times the memory of the full resolution bitmap. Use
+    /// [CziReader::scene_pyramid_iter] to render the layers one at a time instead. The other dimensions are taken
+    /// from the first layer-0 sub-block of the channel in the scene. Layers that libCZI could not identify are skipped.
+    pub fn scene_pyramid(&self, scene: i32, channel: i32) -> Result<Vec<(f32, Bitmap)>> {
+        self.scene_pyramid_iter(scene, channel)?.collect()
+    }
+
+    /// Lazy version of [CziReader::scene_pyramid], rendering each layer when the iterator gets to it.
+    pub fn scene_pyramid_iter(
+        &self,
+        scene: i32,
+        channel: i32,
+    ) -> Result<impl Iterator<Item = Result<(f32, Bitmap)>> + '_> {
+        let mut zooms = self
+            .pyramid_statistics()?
+            .scene_pyramid_statistics
+            .remove(&scene)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|layer| !layer.layer_info.is_not_identified())
+            .map(|layer| layer.layer_info.zoom())
+            .collect::<Vec<_>>();
+        zooms.sort_by(|a, b| b.total_cmp(a));
+
+        let (coordinate, roi) = self
+            .scene_bounds(channel)?
+            .remove(&scene)
+            .ok_or_else(|| anyhow!("no sub-blocks for channel {} in scene {}", channel, scene))?;
+        let accessor = self.create_single_channel_tile_accessor()?;
+        Ok(zooms.into_iter().map(move |zoom| {
+            self.check_region_decode_size(&coordinate, &roi, zoom)?;
+            let bitmap = accessor.get(
+                coordinate.clone(),
+                roi.clone(),
+                zoom,
+                AccessorOptions::default(),
+            )?;
+            Ok((zoom, bitmap))
+        }))
+    }
+
+    /// Render the bounding box of each scene for a channel at the given zoom, as (scene, bitmap) pairs ordered by
+    /// scene. All bitmaps are kept in memory at the same time, so this takes about as much memory as the whole plane
+    /// at this zoom: use [CziReader::scenes_with_bitmaps_iter] to render the scenes one at a time instead. The other
+    /// dimensions are taken from the first layer-0 sub-block of the channel in each scene. A document without an S
+    /// dimension is treated as a single scene 0.
+    pub fn scenes_with_bitmaps(&self, channel: i32, zoom: f32) -> Result<Vec<(i32, Bitmap)>> {
+        self.scenes_with_bitmaps_iter(channel, zoom)?.collect()
+    }
+
+    /// Lazy version of [CziReader::scenes_with_bitmaps], rendering each scene when the iterator gets to it.
+    pub fn scenes_with_bitmaps_iter(
+        &self,
+        channel: i32,
+        zoom: f32,
+    ) -> Result<impl Iterator<Item = Result<(i32, Bitmap)>> + '_> {
+        let scene_bounds = self.scene_bounds(channel)?;
+        let accessor = self.create_single_channel_tile_accessor()?;
+        Ok(scene_bounds
+            .into_iter()
+            .map(move |(scene, (coordinate, roi))| {
+                self.check_region_decode_size(&coordinate, &roi, zoom)?;
+                let bitmap = accessor.get(coordinate, roi, zoom, AccessorOptions::default())?;
+                Ok((scene, bitmap))
+            }))
+    }
+
+    /// For each scene, the coordinate of the first layer-0 sub-block of a channel and the bounding box of all layer-0
+    /// sub-blocks of that channel. Sub-blocks without an S coordinate are counted as scene 0.
+    fn scene_bounds(&self, channel: i32) -> Result<BTreeMap<i32, (Coordinate, IntRect)>> {
+        let mut bounds = BTreeMap::new();
+        for index in 0..self.sub_block_count()? {
+            let info = self.try_get_sub_block_info_for_index(index)?;
+            let coordinate = info.get_coordinate();
+            if info.is_full_resolution() && coordinate.get(Dimension::C).unwrap_or(0) == channel {
+                let rect = info.get_logical_rect();
+                let scene = coordinate.get(Dimension::S).unwrap_or(0);
+                let (x0, y0, x1, y1) = (
+                    rect.get_x(),
+                    rect.get_y(),
+                    rect.get_x() + rect.get_w(),
+                    rect.get_y() + rect.get_h(),
+                );
+                match bounds.entry(scene) {
+                    Entry::Vacant(entry) => {
+                        entry.insert((coordinate, (x0, y0, x1, y1)));
+                    }
+                    Entry::Occupied(mut entry) => {
+                        let (_, bbox) = entry.get_mut();
+                        *bbox = (
+                            bbox.0.min(x0),
+                            bbox.1.min(y0),
+                            bbox.2.max(x1),
+                            bbox.3.max(y1),
+                        );
+                    }
+                }
+            }
+        }
+        Ok(bounds
+            .into_iter()
+            .map(|(scene, (coordinate, (x0, y0, x1, y1)))| {
+                (scene, (coordinate, IntRect::new(x0, y0, x1 - x0, y1 - y0)))
+            })
+            .collect())
+    }
+
+    /// The time stamps stored in the TimeStamps attachment (content file type CZTIMS), one for each T index, as
+    /// stored in the file. None if the document has no such attachment.
+    pub fn time_stamps(&self) -> Result<Option<Vec<f64>>> {
+        for index in 0..self.get_attachment_count()? {
+            if self
+                .get_attachment_info_from_directory(index)?
+                .content_file_type_str()?
+                == "CZTIMS"
+            {
+                let mut data = Vec::new();
+                self.read_attachment(index)?.copy_to(&mut data)?;
+                return Ok(Some(parse_time_stamps(&data)?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the sub-block with the index, decode it into a bitmap and lock the bitmap, see [CziReader::read_sub_block],
+    /// [SubBlock::create_bitmap] and [Bitmap::lock]. The sub-block is released before the locked bitmap is returned.
+    pub fn read_sub_block_as_bitmap(&self, index: i32) -> Result<LockedBitmap> {
+        let bitmap = self.read_sub_block(index)?.create_bitmap()?;
+        bitmap.lock()
+    }
+
+    /// Iterate over all sub-blocks, in the order of their index. The iteration stops early when libCZI returns an
+    /// invalid handle for an index, i.e. when there is no sub-block at that index.
+    pub fn sub_blocks(&self) -> Result<impl Iterator<Item = Result<SubBlock>> + '_> {
+        let sub_block_count = self.sub_block_count()?;
+        Ok(
+            (0..sub_block_count).map_while(move |index| match self.read_sub_block(index) {
+                Ok(sub_block) if *sub_block == INVALID_OBJECT_HANDLE => {
+                    // there is nothing to release
+                    mem::forget(sub_block);
+                    None
+                }
+                result => Some(result),
+            }),
+        )
+    }
+
+    /// Iterate over the sub-blocks on pyramid-layer 0, i.e. the sub-blocks stored at full resolution,
+    /// skipping pyramid tiles (sub-blocks with a downsample factor other than 1).
+    pub fn base_sub_blocks(&self) -> Result<impl Iterator<Item = Result<SubBlock>> + '_> {
+        let sub_block_count = self.sub_block_count()?;
+        Ok((0..sub_block_count).filter_map(move |index| {
+            match self.try_get_sub_block_info_for_index(index) {
+                Ok(info) if info.is_full_resolution() => Some(self.read_sub_block(index)),
+                Ok(_) => None,
+                Err(e) => Some(Err(e)),
+            }
+        }))
+    }
+
     /// Create a single channel scaling tile accessor.
     ///
     /// \\param reader_object            A handle representing the reader-object.
@@ -299,6 +842,67 @@ impl CziReader {
         LibCZIApiError::try_from(unsafe { libCZI_CreateSingleChannelTileAccessor(**self, ptr) })?;
         Ok(unsafe { SingleChannelScalingTileAccessor::assume_init(accessor) })
     }
+
+    /// The first attachment named Thumbnail with content file type JPG or PNG, as written by ZEN. None if the document
+    /// has no such attachment.
+    pub fn get_thumbnail_attachment(&self) -> Result<Option<Attachment>> {
+        self.find_attachment("Thumbnail", &["JPG", "PNG"])
+    }
+
+    /// The first attachment named Label, the image of the slide label as written by ZEN. Its content file type is
+    /// usually CZI, an embedded CZI-document, but can also be JPG or PNG. None if the document has no label.
+    pub fn get_label_attachment(&self) -> Result<Option<Attachment>> {
+        self.find_attachment("Label", &["CZI", "JPG", "PNG"])
+    }
+
+    /// the first attachment with the name and one of the content file types
+    fn find_attachment(
+        &self,
+        name: &str,
+        content_file_types: &[&str],
+    ) -> Result<Option<Attachment>> {
+        for index in 0..self.get_attachment_count()? {
+            let info = self.get_attachment_info_from_directory(index)?;
+            if info.get_name()? == name
+                && content_file_types.contains(&info.content_file_type_str()?)
+            {
+                return Ok(Some(self.read_attachment(index)?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Iterate over all attachment objects, in the order of their index, see [AttachmentIter].
+    pub fn attachments(&self) -> Result<impl Iterator<Item = Result<Attachment>> + '_> {
+        Ok(self
+            .iter_attachments()?
+            .map(|result| result.map(|(_, attachment)| attachment)))
+    }
+
+    /// Iterate over the information of all attachments in the attachment directory, in the order of their index, see
+    /// [AttachmentIter].
+    pub fn attachment_infos(&self) -> Result<impl Iterator<Item = Result<AttachmentInfo>> + '_> {
+        Ok(self
+            .iter_attachments()?
+            .map(|result| result.map(|(info, _)| info)))
+    }
+
+    /// Iterate lazily over the information and the attachment objects of all attachments, see [AttachmentIter].
+    pub fn iter_attachments(&self) -> Result<AttachmentIter<'_>> {
+        Ok(AttachmentIter {
+            reader: self,
+            indices: 0..self.get_attachment_count()?,
+        })
+    }
+
+    /// Iterate lazily over the information of all sub-blocks, see [SubBlockInfoIter].
+    pub fn iter_sub_block_infos(&self) -> SubBlockInfoIter<'_> {
+        SubBlockInfoIter {
+            reader: self,
+            index: 0,
+            done: false,
+        }
+    }
 }
 
 impl Drop for CziReader {
@@ -307,6 +911,123 @@ impl Drop for CziReader {
     }
 }
 
+/// Iterator over the information of the sub-blocks of a reader, see [CziReader::iter_sub_block_infos]. It stops
+/// when libCZI reports the index to be out of range, other errors are yielded once, after which the iterator stops.
+#[derive(Debug)]
+pub struct SubBlockInfoIter<'a> {
+    reader: &'a CziReader,
+    index: i32,
+    done: bool,
+}
+
+impl Iterator for SubBlockInfoIter<'_> {
+    type Item = Result<SubBlockInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.reader.try_get_sub_block_info_for_index(self.index) {
+            Ok(info) => {
+                self.index += 1;
+                Some(Ok(info))
+            }
+            result => {
+                self.done = true;
+                end_if_out_of_range(result)
+            }
+        }
+    }
+}
+
+/// Parse the content of a TimeStamps attachment: the size in bytes and the number of time stamps as i32, followed by
+/// the time stamps as f64.
+pub(crate) fn parse_time_stamps(data: &[u8]) -> Result<Vec<f64>> {
+    let too_short = || Error::msg("TimeStamps attachment is too short");
+    let count = u32::from_le_bytes(data.get(4..8).ok_or_else(too_short)?.try_into()?) as usize;
+    let end = count
+        .checked_mul(8)
+        .and_then(|size| size.checked_add(8))
+        .ok_or_else(|| anyhow!("TimeStamps attachment has too many time stamps: {}", count))?;
+    Ok(data
+        .get(8..end)
+        .ok_or_else(too_short)?
+        .chunks_exact(8)
+        .map(|bytes| f64::from_le_bytes(bytes.try_into().expect("chunk of 8 bytes")))
+        .collect())
+}
+
+/// an error if the decompressed size of a sub-block exceeds max_bytes, a size which is negative or overflows is
+/// treated as exceeding it
+fn check_decode_size(decompressed_size: Result<usize>, max_bytes: usize) -> Result<()> {
+    match decompressed_size {
+        Ok(decompressed_size) if decompressed_size <= max_bytes => Ok(()),
+        Ok(decompressed_size) => Err(anyhow!(
+            "decompressed sub-block size of {} bytes exceeds the limit of {} bytes",
+            decompressed_size,
+            max_bytes
+        )),
+        Err(e) => Err(anyhow!(
+            "decompressed sub-block size exceeds the limit of {} bytes: {}",
+            max_bytes,
+            e
+        )),
+    }
+}
+
+/// None if the result is an IndexOutOfRange error from libCZI, which ends an iteration over indices
+fn end_if_out_of_range<T>(result: Result<T>) -> Option<Result<T>> {
+    match result {
+        Err(e)
+            if matches!(
+                e.downcast_ref::<LibCZIApiError>(),
+                Some(LibCZIApiError::IndexOutOfRange)
+            ) =>
+        {
+            None
+        }
+        result => Some(result),
+    }
+}
+
+/// Iterator over the attachments of a reader, see [CziReader::iter_attachments]. The number of attachments is read
+/// when the iterator is created. It stops when libCZI reports the index to be out of range or returns an invalid
+/// attachment handle, other errors are yielded.
+#[derive(Debug)]
+pub struct AttachmentIter<'a> {
+    reader: &'a CziReader,
+    indices: Range<i32>,
+}
+
+impl Iterator for AttachmentIter<'_> {
+    type Item = Result<(AttachmentInfo, Attachment)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.indices.next()?;
+        let result = self
+            .reader
+            .get_attachment_info_from_directory(index)
+            .and_then(|info| Ok((info, self.reader.read_attachment(index)?)));
+        match end_if_out_of_range(result) {
+            Some(Ok((_, attachment))) if *attachment == INVALID_OBJECT_HANDLE => {
+                // there is nothing to release
+                mem::forget(attachment);
+                self.indices = 0..0;
+                None
+            }
+            None => {
+                self.indices = 0..0;
+                None
+            }
+            result => result,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
 /// Get information about the stream class at the specified index.
 ///
 /// \\param          index                   Zero-based index of the stream class to query information about.
@@ -321,6 +1042,28 @@ pub fn get_stream_classes_count(index: i32) -> Result<InputStreamClassInfo> {
     Ok(unsafe { InputStreamClassInfo::assume_init(input_stream_class_info) })
 }
 
+/// Get the number of available stream classes. This wraps libCZI_GetStreamClassesCount, the name
+/// [get_stream_classes_count] is taken by the function getting the information for one index.
+///
+/// \\param \[out\] count The number of available stream classes it put here.
+///
+/// \\returns An error-code indicating success or failure of the operation.
+pub fn stream_classes_count() -> Result<i32> {
+    let mut count = MaybeUninit::uninit();
+    let ptr = count.as_mut_ptr();
+    LibCZIApiError::try_from(unsafe { libCZI_GetStreamClassesCount(ptr) })?;
+    Ok(unsafe { count.assume_init() })
+}
+
+/// Information about all stream classes available in the linked libCZI, for instance to find out whether streams
+/// reading over http are available. The classes are queried by index (see [get_stream_classes_count], which despite
+/// its name gets the information for one index) up to the number of stream classes (see [stream_classes_count]).
+pub fn enumerate_stream_classes() -> Result<Vec<InputStreamClassInfo>> {
+    (0..stream_classes_count()?)
+        .map(get_stream_classes_count)
+        .collect()
+}
+
 impl InputStream {
     /// Create an input stream object of the specified type, using the specified JSON-formatted property bag and
     /// the specified file identifier as input.
@@ -338,10 +1081,9 @@ impl InputStream {
     ) -> Result<Self> {
         let mut stream = MaybeUninit::uninit();
         let ptr = stream.as_mut_ptr();
-        let stream_class_name = ManuallyDrop::new(CString::new(stream_class_name.as_ref())?);
-        let creation_property_bag =
-            ManuallyDrop::new(CString::new(creation_property_bag.as_ref())?);
-        let stream_identifier = ManuallyDrop::new(CString::new(stream_identifier.as_ref())?);
+        let stream_class_name = CString::new(stream_class_name.as_ref())?;
+        let creation_property_bag = CString::new(creation_property_bag.as_ref())?;
+        let stream_identifier = CString::new(stream_identifier.as_ref())?;
         LibCZIApiError::try_from(unsafe {
             libCZI_CreateInputStream(
                 stream_class_name.as_ptr(),
@@ -380,8 +1122,7 @@ impl InputStream {
     pub fn create_from_file_utf8<S: AsRef<str>>(file_name: S) -> Result<Self> {
         let mut stream = MaybeUninit::uninit();
         let ptr = stream.as_mut_ptr();
-        let file_name = ManuallyDrop::new(CString::new(file_name.as_ref())?);
-        // let file_name = file_name.as_ref().as_bytes().to_vec();
+        let file_name = CString::new(file_name.as_ref())?;
         LibCZIApiError::try_from(unsafe {
             libCZI_CreateInputStreamFromFileUTF8(file_name.as_ptr() as *const c_char, ptr)
         })?;
@@ -405,6 +1146,39 @@ impl InputStream {
         Ok(unsafe { Self::assume_init(stream) })
     }
 
+    /// Create an input stream reading from data in memory. The stream keeps a reference to data until libCZI closes
+    /// it, which can be after the InputStream is dropped if a reader still uses it.
+    pub fn create_from_memory(data: Arc<[u8]>) -> Result<Self> {
+        // if creating the stream fails, it is unknown whether libCZI called the close function, so the data is leaked
+        // rather than risking a double free
+        let opaque_handle = Box::into_raw(Box::new(data));
+        Self::create_from_external(ExternalInputStreamStruct(
+            ExternalInputStreamStructInterop {
+                opaque_handle1: opaque_handle as c_ulong,
+                opaque_handle2: 0,
+                read_function: Some(memory_input_stream_read),
+                close_function: Some(memory_input_stream_close),
+            },
+        ))
+    }
+
+    /// Create an input stream reading from reader. libCZI reads at arbitrary offsets, so the reader is seeked before
+    /// each read, and it may do so from several threads, so access to the reader is serialized. The reader is dropped
+    /// when libCZI closes the stream, which can be after the InputStream is dropped if a reader still uses it. I/O
+    /// errors are passed on to libCZI, which reports them as a failure of the operation reading the data.
+    pub fn from_reader<R: Read + Seek + Send + 'static>(reader: R) -> Result<Self> {
+        // as for create_from_memory, the reader is leaked if creating the stream fails
+        let opaque_handle = Box::into_raw(Box::new(Mutex::new(reader)));
+        Self::create_from_external(ExternalInputStreamStruct(
+            ExternalInputStreamStructInterop {
+                opaque_handle1: opaque_handle as c_ulong,
+                opaque_handle2: 0,
+                read_function: Some(reader_input_stream_read::<R>),
+                close_function: Some(reader_input_stream_close::<R>),
+            },
+        ))
+    }
+
     /// Release the specified input stream object. After this function is called, the handle is no
     /// longer valid. Note that calling this function will only decrement the usage count of the
     /// underlying object; whereas the object itself (and the resources it holds) will only be
@@ -425,22 +1199,142 @@ impl Drop for InputStream {
     }
 }
 
+/// read function of the stream created by [InputStream::create_from_memory], opaque_handle1 points to the data
+unsafe extern "C" fn memory_input_stream_read(
+    opaque_handle1: c_ulong,
+    _opaque_handle2: c_ulong,
+    offset: c_ulong,
+    pv: *mut c_void,
+    size: c_ulong,
+    ptr_bytes_read: *mut c_ulong,
+    _error_info: *mut ExternalStreamErrorInfoInterop,
+) -> c_int {
+    let data = unsafe { &*(opaque_handle1 as *const Arc<[u8]>) };
+    let start = (offset as usize).min(data.len());
+    let bytes_read = if pv.is_null() {
+        0
+    } else {
+        (size as usize).min(data.len() - start)
+    };
+    if bytes_read > 0 {
+        unsafe { ptr::copy_nonoverlapping(data[start..].as_ptr(), pv as *mut u8, bytes_read) };
+    }
+    if !ptr_bytes_read.is_null() {
+        unsafe { *ptr_bytes_read = bytes_read as c_ulong };
+    }
+    0
+}
+
+/// close function of the stream created by [InputStream::create_from_memory], frees the reference to the data
+unsafe extern "C" fn memory_input_stream_close(opaque_handle1: c_ulong, _opaque_handle2: c_ulong) {
+    drop(unsafe { Box::from_raw(opaque_handle1 as *mut Arc<[u8]>) });
+}
+
+/// read function of the stream created by [InputStream::from_reader], opaque_handle1 points to the reader
+unsafe extern "C" fn reader_input_stream_read<R: Read + Seek>(
+    opaque_handle1: c_ulong,
+    _opaque_handle2: c_ulong,
+    offset: c_ulong,
+    pv: *mut c_void,
+    size: c_ulong,
+    ptr_bytes_read: *mut c_ulong,
+    error_info: *mut ExternalStreamErrorInfoInterop,
+) -> c_int {
+    if size == 0 || pv.is_null() {
+        if !ptr_bytes_read.is_null() {
+            unsafe { *ptr_bytes_read = 0 };
+        }
+        return 0;
+    }
+    let reader = unsafe { &*(opaque_handle1 as *const Mutex<R>) };
+    let buffer = unsafe { slice::from_raw_parts_mut(pv as *mut u8, size as usize) };
+    let result = reader
+        .lock()
+        .map_err(|_| io::Error::other("reader is poisoned"))
+        .and_then(|mut reader| {
+            reader.seek(SeekFrom::Start(offset as _))?;
+            // fill the buffer as far as possible, stopping only at the end of the stream
+            let mut bytes_read = 0;
+            while bytes_read < buffer.len() {
+                match reader.read(&mut buffer[bytes_read..]) {
+                    Ok(0) => break,
+                    Ok(n) => bytes_read += n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(bytes_read)
+        });
+    match result {
+        Ok(bytes_read) => {
+            if !ptr_bytes_read.is_null() {
+                unsafe { *ptr_bytes_read = bytes_read as c_ulong };
+            }
+            0
+        }
+        Err(e) => set_external_stream_error(error_info, e),
+    }
+}
+
+/// close function of the stream created by [InputStream::from_reader], drops the reader
+unsafe extern "C" fn reader_input_stream_close<R: Read + Seek>(
+    opaque_handle1: c_ulong,
+    _opaque_handle2: c_ulong,
+) {
+    drop(unsafe { Box::from_raw(opaque_handle1 as *mut Mutex<R>) });
+}
+
+/// Put the error into the error information for libCZI, if given, and return the value signalling failure to libCZI.
+fn set_external_stream_error(
+    error_info: *mut ExternalStreamErrorInfoInterop,
+    error: io::Error,
+) -> c_int {
+    if !error_info.is_null() {
+        if let Ok(info) =
+            ExternalStreamErrorInfo::new(kStreamErrorCode_UnspecifiedError, error.to_string())
+        {
+            unsafe { ptr::write_unaligned(error_info, info.into_raw()) };
+        }
+    }
+    kStreamErrorCode_UnspecifiedError
+}
+
 impl SubBlock {
     /// Create a bitmap object from the specified sub-block object. The bitmap object can be used to access the pixel
     /// data contained in the sub-block. If the subblock contains compressed data, then decompression will be performed
-    /// in this call.
+    /// in this call. If the reader the sub-block was read from has a limit on the size of decoded data (see
+    /// [CziReader::set_max_decode_bytes]), an error is returned instead of decoding a sub-block exceeding it.
     ///
     /// \\param          sub_block_object The sub-block object.
     /// \\param \[out\]    bitmap_object    If successful, the handle to the newly created bitmap object is put here.
     ///
     /// \\returns An error-code indicating success or failure of the operation.
     pub fn create_bitmap(&self) -> Result<Bitmap> {
+        if let Some(max_bytes) = self.max_decode_bytes {
+            check_decode_size(self.get_info()?.decompressed_size(), max_bytes)?;
+        }
         let mut bitmap = MaybeUninit::uninit();
         let ptr = bitmap.as_mut_ptr();
         LibCZIApiError::try_from(unsafe { libCZI_SubBlockCreateBitmap(**self, ptr) })?;
         Ok(unsafe { Bitmap::assume_init(bitmap) })
     }
 
+    /// Create a bitmap object from the sub-block like [SubBlock::create_bitmap], but first check that the decompressed
+    /// pixel data does not exceed max_bytes, and return an error instead of decoding if it does. Use this to guard
+    /// against corrupt or malicious files claiming enormous bitmap sizes. A sub-block whose size is negative or
+    /// overflows is treated as exceeding the limit.
+    pub fn create_bitmap_limited(&self, max_bytes: usize) -> Result<Bitmap> {
+        check_decode_size(self.get_info()?.decompressed_size(), max_bytes)?;
+        self.create_bitmap()
+    }
+
+    /// Decode the sub-block and return the information of the bitmap together with a copy of its pixels, with the
+    /// rows packed without padding (see [Bitmap::to_vec]). The bitmap is released before returning.
+    pub fn pixel_data(&self) -> Result<(BitmapInfo, Vec<u8>)> {
+        let bitmap = self.create_bitmap()?;
+        Ok((bitmap.get_info()?, bitmap.to_vec()?))
+    }
+
     /// Get Information about the sub-block.
     ///
     /// \\param       sub_block_object The sub-block object.
@@ -468,13 +1362,40 @@ impl SubBlock {
     /// \\param \[out\]    data                Pointer where the data is to be copied to. At most the initial content of 'size' bytes are copied.
     ///
     /// \\returns    An error-code indicating success or failure of the operation.
+    ///
+    /// Returns the actual size of the available data and the copied data, which is truncated to at most size bytes.
+    /// With a size of zero (0), the returned Vec is empty and only the required size is returned.
     pub fn get_raw_data(&self, tp: RawDataType, size: i32) -> Result<(i32, Vec<u8>)> {
-        let mut data = Vec::<u8>::with_capacity(size as usize);
-        let size = Box::into_raw(Box::new(size as c_ulong));
+        let capacity = size.max(0) as usize;
+        let mut data = Vec::<u8>::with_capacity(capacity);
+        let mut size = capacity as c_ulong;
+        let ptr = if capacity == 0 {
+            ptr::null_mut()
+        } else {
+            data.as_mut_ptr() as *mut c_void
+        };
         LibCZIApiError::try_from(unsafe {
-            libCZI_SubBlockGetRawData(**self, tp as c_int, size, data.as_mut_ptr() as *mut c_void)
+            libCZI_SubBlockGetRawData(**self, tp as c_int, &mut size, ptr)
         })?;
-        Ok((unsafe { *Box::from_raw(size) as i32 }, data))
+        unsafe { data.set_len((size as usize).min(capacity)) };
+        Ok((size as i32, data))
+    }
+
+    /// Ratio of the size of the decompressed pixel data to the size of the pixel data as stored in the file, 1.0 for
+    /// uncompressed sub-blocks. An error is returned for a compressed sub-block without stored pixel data.
+    pub fn compression_ratio(&self) -> Result<f64> {
+        let info = self.get_info()?;
+        if let CompressionMode::UnCompressed = info.get_compression_mode()? {
+            return Ok(1.0);
+        }
+        let (size, _) = self.get_raw_data(RawDataType::Data, 0)?;
+        if size <= 0 {
+            return Err(anyhow!(
+                "compressed sub-block has a data size of {} bytes",
+                size
+            ));
+        }
+        Ok(info.decompressed_size()? as f64 / size as f64)
     }
 
     /// Release the specified sub-block object.
@@ -506,22 +1427,32 @@ impl Attachment {
         Ok(unsafe { AttachmentInfo::assume_init(attachment_info) })
     }
 
-    /// Copy the raw data from the specified attachment object to the specified memory buffer. The value of the 'size' parameter
-    /// on input is the size of the buffer pointed to by 'data'. On output, the value of 'size' is the actual size of the data. At most
-    /// the initial value of 'size' bytes are copied to the buffer. If the initial value of 'size' is zero (0) or 'data' is null, then
-    /// no data is copied.
-    /// \\param          attachment_object   The attachment object.
-    /// \\param \[in,out\] size                On input, the size of the memory block pointed to by 'data', on output the actual size of the available data.
-    /// \\param \[out\]    data                Pointer where the data is to be copied to. At most the initial content of 'size' bytes are copied.
-    ///
-    /// \\returns    An error-code indicating success or failure of the operation.
-    pub fn get_raw_data(&self, size: i32) -> Result<(i32, Vec<u8>)> {
-        let mut data = Vec::<u8>::with_capacity(size as usize);
-        let size = Box::into_raw(Box::new(size as c_ulong));
+    /// Copy the raw data of the attachment. The size is queried first, then a buffer of exactly that size is
+    /// allocated and the data copied into it. An empty attachment gives an empty Vec.
+    pub fn get_raw_data(&self) -> Result<Vec<u8>> {
+        let mut size: c_ulong = 0;
         LibCZIApiError::try_from(unsafe {
-            libCZI_AttachmentGetRawData(**self, size, data.as_mut_ptr() as *mut c_void)
+            libCZI_AttachmentGetRawData(**self, &mut size, ptr::null_mut())
         })?;
-        Ok((unsafe { *Box::from_raw(size) as i32 }, data))
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+        let capacity = size as usize;
+        let mut data = Vec::<u8>::with_capacity(capacity);
+        LibCZIApiError::try_from(unsafe {
+            libCZI_AttachmentGetRawData(**self, &mut size, data.as_mut_ptr() as *mut c_void)
+        })?;
+        unsafe { data.set_len((size as usize).min(capacity)) };
+        Ok(data)
+    }
+
+    /// Copy the raw data of the attachment to the writer w and return the number of bytes copied. Note that the C API
+    /// cannot read part of an attachment, so the data is read into memory at once (see [Attachment::get_raw_data])
+    /// before it is written to w.
+    pub fn copy_to<W: Write>(&self, w: &mut W) -> Result<u64> {
+        let data = self.get_raw_data()?;
+        w.write_all(&data)?;
+        Ok(data.len() as u64)
     }
 
     /// Release the specified attachment object.
@@ -575,6 +1506,42 @@ impl Bitmap {
         })
     }
 
+    /// A copy of the pixel data, with the rows packed without padding, so it has width * height * bytes per pixel
+    /// bytes. The bitmap is locked while copying and unlocked before returning.
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        let info = self.get_info()?;
+        let row_size = info.get_width() as usize * info.get_pixel_type()?.bytes_per_pixel();
+        let height = info.get_height() as usize;
+        let mut lock_info = MaybeUninit::uninit();
+        LibCZIApiError::try_from(unsafe { libCZI_BitmapLock(**self, lock_info.as_mut_ptr()) })?;
+        let lock_info = unsafe { BitmapLockInfo::assume_init(lock_info) };
+        let stride = lock_info.get_stride() as usize;
+        let result = if height > 0
+            && (lock_info.0.ptrDataRoi.is_null()
+                || stride < row_size
+                || (height - 1) * stride + row_size > lock_info.data_len())
+        {
+            Err(Error::msg(
+                "bitmap data is smaller than its size and stride imply",
+            ))
+        } else {
+            let mut data = Vec::<u8>::with_capacity(row_size * height);
+            for row in 0..height {
+                unsafe {
+                    ptr::copy_nonoverlapping(
+                        (lock_info.0.ptrDataRoi as *const u8).add(row * stride),
+                        data.as_mut_ptr().add(row * row_size),
+                        row_size,
+                    );
+                }
+            }
+            unsafe { data.set_len(row_size * height) };
+            Ok(data)
+        };
+        LibCZIApiError::try_from(unsafe { libCZI_BitmapUnlock(**self) })?;
+        result
+    }
+
     /// Release the specified bitmap object.
     /// It is a fatal error trying to release a bitmap object that is still locked.
     ///
@@ -621,7 +1588,146 @@ impl Drop for LockedBitmap {
     }
 }
 
+/// Iterator over the rows of a locked bitmap, see [LockedBitmap::iter_rows].
+#[derive(Clone, Debug)]
+pub struct RowIter<'a> {
+    data: &'a [u8],
+    row_size: usize,
+    stride: usize,
+    rows: Range<usize>,
+}
+
+impl<'a> RowIter<'a> {
+    fn row(&self, row: usize) -> &'a [u8] {
+        &self.data[row * self.stride..row * self.stride + self.row_size]
+    }
+}
+
+impl<'a> Iterator for RowIter<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next().map(|row| self.row(row))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.rows.size_hint()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.rows.nth(n).map(|row| self.row(row))
+    }
+}
+
+impl DoubleEndedIterator for RowIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.rows.next_back().map(|row| self.row(row))
+    }
+}
+
+impl ExactSizeIterator for RowIter<'_> {}
+
 impl LockedBitmap {
+    /// The locked pixel data, borrowed from the bitmap. Rows start every stride bytes, so the data can include
+    /// padding at the end of each row, use [LockedBitmap::rows] to get the pixels only.
+    pub fn data(&self) -> &[u8] {
+        if self.lock_info.0.ptrDataRoi.is_null() {
+            return &[];
+        }
+        unsafe {
+            slice::from_raw_parts(
+                self.lock_info.0.ptrDataRoi as *const u8,
+                self.lock_info.data_len(),
+            )
+        }
+    }
+
+    /// The locked pixel data reinterpreted as elements of type T, for example u16 for a gray16 bitmap, available with
+    /// the feature 'bytemuck'. Like [LockedBitmap::data], this includes any padding up to the stride. None is returned
+    /// if the data is not a whole number of elements, or is not aligned for T.
+    #[cfg(feature = "bytemuck")]
+    pub fn as_typed_slice<T: bytemuck::Pod>(&self) -> Option<&[T]> {
+        bytemuck::try_cast_slice(self.data()).ok()
+    }
+
+    /// Iterate over the rows of the bitmap from top to bottom, each row being the bytes of its pixels without the
+    /// padding up to the stride.
+    pub fn iter_rows(&self) -> Result<RowIter<'_>> {
+        let (row_size, height, stride) = self.row_layout()?;
+        Ok(RowIter {
+            data: self.data(),
+            row_size,
+            stride,
+            rows: 0..height,
+        })
+    }
+
+    /// Iterate over the rows of the bitmap, the same as [LockedBitmap::iter_rows].
+    pub fn rows(&self) -> Result<RowIter<'_>> {
+        self.iter_rows()
+    }
+
+    /// Row y of the bitmap, being the bytes of its pixels without the padding up to the stride. An error is
+    /// returned if y is not smaller than the height of the bitmap.
+    pub fn row(&self, y: u32) -> Result<&[u8]> {
+        let (row_size, height, stride) = self.row_layout()?;
+        let y = y as usize;
+        if y >= height {
+            return Err(anyhow!(
+                "row {} is out of range for a bitmap of height {}",
+                y,
+                height
+            ));
+        }
+        Ok(&self.data()[y * stride..y * stride + row_size])
+    }
+
+    /// Copy the pixels in roi, given in pixels relative to the top left corner of the bitmap, into a Vec. The rows
+    /// are packed without padding. An error is returned if roi is not fully inside the bitmap.
+    pub fn copy_region(&self, roi: IntRect) -> Result<Vec<u8>> {
+        let info = self.get_info()?;
+        let (x, y, w, h) = (roi.get_x(), roi.get_y(), roi.get_w(), roi.get_h());
+        if x < 0
+            || y < 0
+            || w < 0
+            || h < 0
+            || x as u32 + w as u32 > info.get_width()
+            || y as u32 + h as u32 > info.get_height()
+        {
+            return Err(anyhow!(
+                "region ({}, {}, {}, {}) is not inside the bitmap of {}x{} pixels",
+                x,
+                y,
+                w,
+                h,
+                info.get_width(),
+                info.get_height()
+            ));
+        }
+        let bytes_per_pixel = info.get_pixel_type()?.bytes_per_pixel();
+        let (start, size) = (x as usize * bytes_per_pixel, w as usize * bytes_per_pixel);
+        let mut region = Vec::with_capacity(size * h as usize);
+        for row in self.rows()?.skip(y as usize).take(h as usize) {
+            region.extend_from_slice(&row[start..start + size]);
+        }
+        Ok(region)
+    }
+
+    /// the size in bytes of a row of pixels, the number of rows and the stride, checked against the size of the data
+    fn row_layout(&self) -> Result<(usize, usize, usize)> {
+        let info = self.get_info()?;
+        let row_size = info.get_width() as usize * info.get_pixel_type()?.bytes_per_pixel();
+        let height = info.get_height() as usize;
+        let stride = self.lock_info.get_stride() as usize;
+        if height > 0 && (stride < row_size || (height - 1) * stride + row_size > self.data().len())
+        {
+            return Err(Error::msg(
+                "bitmap data is smaller than its size and stride imply",
+            ));
+        }
+        Ok((row_size, height, stride))
+    }
+
     /// Unlock the bitmap object. Once the bitmap is unlocked, the pixel data must not be accessed anymore.
     ///
     /// \\param  bitmap_object The bitmap object.
@@ -629,7 +1735,9 @@ impl LockedBitmap {
     /// \\returns An error-code indicating success or failure of the operation.
     pub fn unlock(self) -> Result<Bitmap> {
         LibCZIApiError::try_from(unsafe { libCZI_BitmapUnlock(**self) })?;
-        Ok(self.bitmap.clone())
+        // move the bitmap out without running Drop, which would unlock again and release the bitmap
+        let locked = ManuallyDrop::new(self);
+        Ok(unsafe { ptr::read(&locked.bitmap) })
     }
 
     /// Copy the pixel data from the specified bitmap object to the specified memory buffer. The specified
@@ -643,14 +1751,33 @@ impl LockedBitmap {
     /// \\param \[out\]    ptr           Pointer to the memory location where the bitmap is to be copied to.
     ///
     /// \\returns A LibCZIApiErrorCode.
+    ///
+    /// An error is returned if data is too small for a bitmap of height rows of stride bytes, or if the stride is
+    /// smaller than a row of pixels.
     pub fn copy(
         &self,
         width: u32,
         height: u32,
         pixel_type: PixelType,
         stride: u32,
-    ) -> Result<Bitmap> {
-        let mut data = MaybeUninit::<Self>::uninit();
+        data: &mut [u8],
+    ) -> Result<()> {
+        let row_size = width as usize * pixel_type.bytes_per_pixel();
+        if (stride as usize) < row_size {
+            return Err(anyhow!(
+                "stride {} is smaller than a row of {} bytes",
+                stride,
+                row_size
+            ));
+        }
+        if height > 0 && (height as usize - 1) * stride as usize + row_size > data.len() {
+            return Err(anyhow!(
+                "buffer of {} bytes is too small for {} rows with stride {}",
+                data.len(),
+                height,
+                stride
+            ));
+        }
         LibCZIApiError::try_from(unsafe {
             libCZI_BitmapCopyTo(
                 ***self,
@@ -661,7 +1788,7 @@ impl LockedBitmap {
                 data.as_mut_ptr() as *mut c_void,
             )
         })?;
-        Ok(unsafe { data.assume_init().unlock()? })
+        Ok(())
     }
 }
 
@@ -788,10 +1915,38 @@ impl CziDocumentInfo {
             )
         })?;
         let ptr = unsafe { ptr.assume_init() };
-        assert!(!ptr.is_null());
-        Ok(unsafe { CStr::from_ptr(ptr) }
+        if ptr.is_null() {
+            return Ok(String::new());
+        }
+        let info = unsafe { CStr::from_ptr(ptr) }
             .to_string_lossy()
-            .into_owned())
+            .into_owned();
+        unsafe { libCZI_Free(ptr as *mut c_void) };
+        Ok(info)
+    }
+
+    /// The dimension information (see [CziDocumentInfo::get_dimension_info]) parsed into a [DimensionInfo], None if
+    /// libCZI returns no information for the dimension, i.e. an empty string or an empty object.
+    pub fn dimension_info(&self, dimension: Dimension) -> Result<Option<DimensionInfo>> {
+        // the dimension index used by libCZI is the discriminant of Dimension
+        let json = self.get_dimension_info(dimension as u32)?;
+        if json.trim().is_empty() {
+            return Ok(None);
+        }
+        let dimension_info: DimensionInfo = json.parse()?;
+        Ok((!dimension_info.properties.is_empty()).then_some(dimension_info))
+    }
+
+    /// The dimension information of all dimensions for which libCZI returns information, see
+    /// [CziDocumentInfo::dimension_info].
+    pub fn dimension_infos(&self) -> impl Iterator<Item = Result<(Dimension, DimensionInfo)>> + '_ {
+        Dimension::vec_from_bitflags(0x1ff)
+            .into_iter()
+            .filter_map(|dimension| match self.dimension_info(dimension.clone()) {
+                Ok(Some(dimension_info)) => Some(Ok((dimension, dimension_info))),
+                Ok(None) => None,
+                Err(e) => Some(Err(e)),
+            })
     }
 
     /// Release the specified CZI-document-info object.
@@ -842,7 +1997,7 @@ impl OutputStream {
     pub fn create_for_file_utf8<S: AsRef<str>>(file_name: S, overwrite: bool) -> Result<Self> {
         let mut output_stream = MaybeUninit::uninit();
         let ptr = output_stream.as_mut_ptr();
-        let file_name = ManuallyDrop::new(CString::new(file_name.as_ref())?);
+        let file_name = CString::new(file_name.as_ref())?;
         LibCZIApiError::try_from(unsafe {
             libCZI_CreateOutputStreamForFileUTF8(file_name.as_ptr(), overwrite, ptr)
         })?;
@@ -878,6 +2033,127 @@ impl OutputStream {
         })?;
         Ok(unsafe { Self::assume_init(stream) })
     }
+
+    /// Create an output stream writing to writer. libCZI writes at arbitrary offsets, so the writer is seeked before
+    /// each write, and access to the writer is serialized. The writer is flushed after each write, so that errors of a
+    /// buffered writer surface in the write causing them, and dropped when libCZI closes the stream, which is when
+    /// the OutputStream and the writer using it are released. I/O errors are passed on to libCZI, which reports them
+    /// as a failure of the operation writing the data.
+    pub fn from_writer<W: Write + Seek + Send + 'static>(writer: W) -> Result<Self> {
+        // as for InputStream::create_from_memory, the writer is leaked if creating the stream fails
+        let opaque_handle = Box::into_raw(Box::new(Mutex::new(writer)));
+        Self::create_from_external(ExternalOutputStreamStruct(
+            ExternalOutputStreamStructInterop {
+                opaque_handle1: opaque_handle as c_ulong,
+                opaque_handle2: 0,
+                write_function: Some(writer_output_stream_write::<W>),
+                close_function: Some(writer_output_stream_close::<W>),
+            },
+        ))
+    }
+
+    /// Create an output stream writing into memory. The returned buffer holds the bytes written so far, it is
+    /// complete when the writer using the stream is closed.
+    pub fn create_in_memory() -> Result<(Self, Arc<Mutex<Vec<u8>>>)> {
+        let data = Arc::new(Mutex::new(Vec::new()));
+        let stream = Self::from_writer(MemoryWriter {
+            data: data.clone(),
+            position: 0,
+        })?;
+        Ok((stream, data))
+    }
+}
+
+/// writer of the stream created by [OutputStream::create_in_memory], writing at a position in shared data
+struct MemoryWriter {
+    data: Arc<Mutex<Vec<u8>>>,
+    position: u64,
+}
+
+impl Write for MemoryWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut data = self
+            .data
+            .lock()
+            .map_err(|_| io::Error::other("data is poisoned"))?;
+        let start = self.position as usize;
+        let end = start + buf.len();
+        if data.len() < end {
+            // libCZI may skip ahead, the gap is filled with zeros
+            data.resize(end, 0);
+        }
+        data[start..end].copy_from_slice(buf);
+        self.position = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let length = self
+            .data
+            .lock()
+            .map_err(|_| io::Error::other("data is poisoned"))?
+            .len() as i64;
+        let position = match pos {
+            SeekFrom::Start(position) => position as i64,
+            SeekFrom::End(offset) => length + offset,
+            SeekFrom::Current(offset) => self.position as i64 + offset,
+        };
+        if position < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek to a negative position",
+            ));
+        }
+        self.position = position as u64;
+        Ok(self.position)
+    }
+}
+
+/// write function of the stream created by [OutputStream::from_writer], opaque_handle1 points to the writer
+unsafe extern "C" fn writer_output_stream_write<W: Write + Seek>(
+    opaque_handle1: c_ulong,
+    _opaque_handle2: c_ulong,
+    offset: c_ulong,
+    pv: *const c_void,
+    size: c_ulong,
+    out_bytes_written: *mut c_ulong,
+    error_info: *mut ExternalStreamErrorInfoInterop,
+) -> c_int {
+    let writer = unsafe { &*(opaque_handle1 as *const Mutex<W>) };
+    let buffer = unsafe { slice::from_raw_parts(pv as *const u8, size as usize) };
+    let result = writer
+        .lock()
+        .map_err(|_| io::Error::other("writer is poisoned"))
+        .and_then(|mut writer| {
+            writer.seek(SeekFrom::Start(offset as _))?;
+            writer.write_all(buffer)?;
+            // libCZI cannot be told about an error when closing the stream, so flushing is not deferred until then
+            writer.flush()
+        });
+    match result {
+        Ok(()) => {
+            if !out_bytes_written.is_null() {
+                unsafe { *out_bytes_written = size };
+            }
+            0
+        }
+        Err(e) => set_external_stream_error(error_info, e),
+    }
+}
+
+/// close function of the stream created by [OutputStream::from_writer], drops the writer, which was flushed by the
+/// last write
+unsafe extern "C" fn writer_output_stream_close<W: Write + Seek>(
+    opaque_handle1: c_ulong,
+    _opaque_handle2: c_ulong,
+) {
+    drop(unsafe { Box::from_raw(opaque_handle1 as *mut Mutex<W>) });
 }
 
 impl Drop for OutputStream {
@@ -902,7 +2178,7 @@ impl CziWriter {
     pub fn create<S: AsRef<str>>(options: S) -> Result<Self> {
         let mut writer = MaybeUninit::uninit();
         let ptr = writer.as_mut_ptr();
-        let options = ManuallyDrop::new(CString::new(options.as_ref())?);
+        let options = CString::new(options.as_ref())?;
         LibCZIApiError::try_from(unsafe { libCZI_CreateWriter(ptr, options.as_ptr()) })?;
         Ok(unsafe { Self::assume_init(writer) })
     }
@@ -918,21 +2194,87 @@ impl CziWriter {
     /// \"maximum_m_index\" : 100
     /// }
     /// \\endcode
+    /// The writer is consumed and returned as an initialized writer, only to which sub-blocks, attachments and metadata can be added.
     ///
     /// \\param \[out\] writer_object If the operation is successful, a handle to the newly created writer object is put here.
     /// \\param       output_stream_object The output stream object to be used for writing the CZI data.
     /// \\param       parameters       A JSON-formatted zero-terminated string (in UTF8-encoding) containing options for the writer initialization.
     ///
     /// \\returns An error-code indicating success or failure of the operation.
-    pub fn init<S: AsRef<str>>(&self, output_stream: &OutputStream, parameters: S) -> Result<()> {
-        let parameters = ManuallyDrop::new(CString::new(parameters.as_ref())?);
+    pub fn init<S: AsRef<str>>(
+        self,
+        output_stream: &OutputStream,
+        parameters: S,
+    ) -> Result<InitializedCziWriter> {
+        let parameters = CString::new(parameters.as_ref())?;
         LibCZIApiError::try_from(unsafe {
-            libCZI_WriterCreate(**self, **output_stream, parameters.as_ptr())
+            libCZI_WriterCreate(*self, **output_stream, parameters.as_ptr())
         })?;
+        Ok(InitializedCziWriter {
+            writer: self,
+            attachment_capacity: None,
+            attachment_count: Cell::new(0),
+        })
+    }
+
+    /// Initializes the writer object with the specified output stream object and typed options, see
+    /// [CziWriter::init]. If space for the attachment directory is reserved, the initialized writer refuses to add
+    /// more attachments than fit in it (see [WriterInitOptions::attachment_capacity]).
+    pub fn init_with_options(
+        self,
+        output_stream: &OutputStream,
+        options: &WriterInitOptions,
+    ) -> Result<InitializedCziWriter> {
+        let mut writer = self.init(output_stream, options.to_json())?;
+        writer.attachment_capacity = options.attachment_capacity();
+        Ok(writer)
+    }
+
+    /// Release the specified writer object.
+    ///
+    /// \\param  writer_object Handle to the writer object that is to be released.
+    ///
+    /// \\returns    An error-code indicating success or failure of the operation.
+    ///
+    /// The writer is released when it is dropped, releasing it before would release the handle twice, and an
+    /// [InitializedCziWriter] would close a released writer.
+    pub(crate) fn release(&self) -> Result<()> {
+        LibCZIApiError::try_from(unsafe { libCZI_ReleaseWriter(**self) })?;
         Ok(())
     }
+}
+
+impl Drop for CziWriter {
+    fn drop(&mut self) {
+        self.release().ok();
+    }
+}
+
+/// Initialized version of writer so that data can be written
+pub struct InitializedCziWriter {
+    writer: CziWriter,
+    attachment_capacity: Option<usize>,
+    attachment_count: Cell<usize>,
+}
+
+impl Deref for InitializedCziWriter {
+    type Target = CziWriter;
+
+    fn deref(&self) -> &Self::Target {
+        &self.writer
+    }
+}
 
+impl Drop for InitializedCziWriter {
+    fn drop(&mut self) {
+        unsafe { libCZI_WriterClose(*self.writer) };
+    }
+}
+
+impl InitializedCziWriter {
     /// Add the specified sub-block to the writer object. The sub-block information is provided in the 'add_sub_block_info_interop' structure.
+    /// Note that the writer does not compress the data: if the compression mode is not 'UnCompressed', then the data must
+    /// already be compressed accordingly, it is written as is.
     ///
     /// \\param  writer_object               The writer object.
     /// \\param  add_sub_block_info_interop  Information describing the sub-block to be added.
@@ -940,7 +2282,7 @@ impl CziWriter {
     /// \\returns    An error-code indicating success or failure of the operation.
     pub fn add_sub_block(&self, add_sub_block_info: AddSubBlockInfo) -> Result<()> {
         LibCZIApiError::try_from(unsafe {
-            libCZI_WriterAddSubBlock(**self, add_sub_block_info.as_ptr())
+            libCZI_WriterAddSubBlock(***self, add_sub_block_info.as_ptr())
         })?;
         Ok(())
     }
@@ -952,9 +2294,18 @@ impl CziWriter {
     ///
     /// \\returns    An error-code indicating success or failure of the operation.
     pub fn add_attachement(&self, add_attachment_info: AddAttachmentInfo) -> Result<()> {
+        if let Some(capacity) = self.attachment_capacity {
+            if self.attachment_count.get() >= capacity {
+                return Err(Error::msg(format!(
+                    "the reserved attachment directory can hold only {} attachments",
+                    capacity
+                )));
+            }
+        }
         LibCZIApiError::try_from(unsafe {
-            libCZI_WriterAddAttachment(**self, add_attachment_info.as_ptr())
+            libCZI_WriterAddAttachment(***self, add_attachment_info.as_ptr())
         })?;
+        self.attachment_count.set(self.attachment_count.get() + 1);
         Ok(())
     }
 
@@ -966,41 +2317,89 @@ impl CziWriter {
     /// \\returns    An error-code indicating success or failure of the operation.
     pub fn write_metadata(&self, write_metadata_info: WriteMetadataInfo) -> Result<()> {
         LibCZIApiError::try_from(unsafe {
-            libCZI_WriterWriteMetadata(**self, write_metadata_info.as_ptr())
+            libCZI_WriterWriteMetadata(***self, write_metadata_info.as_ptr())
         })?;
         Ok(())
     }
 
-    /// inalizes the CZI (i.e. writes out the final directory-segments) and closes the file.
-    /// Note that this method must be called explicitly in order to get a valid CZI - calling 'libCZI_ReleaseWriter' without
-    /// a prior call to this method will close the file immediately without finalization.
-    ///
-    /// \\param  writer_object   Handle to the writer object that is to be closed.
-    ///
-    /// \\returns    An error-code indicating success or failure of the operation.
-    pub fn close(&self) -> Result<()> {
-        LibCZIApiError::try_from(unsafe { libCZI_WriterClose(**self) })?;
-        Ok(())
+    /// Add the pixel data of a locked bitmap as a sub-block with its top-left corner at x, y, with the given coordinate
+    /// and M-index. The rows of the bitmap are packed to the minimal stride. libCZI expects the data of a sub-block to
+    /// be compressed already and libCZIAPI has no functions to compress it, so only CompressionMode::UnCompressed is
+    /// supported, an error is returned for any other compression mode.
+    pub fn add_bitmap(
+        &self,
+        coordinate: Coordinate,
+        x: i32,
+        y: i32,
+        m_index: Option<i32>,
+        bitmap: &LockedBitmap,
+        compression: CompressionMode,
+    ) -> Result<()> {
+        if !matches!(compression, CompressionMode::UnCompressed) {
+            return Err(anyhow!(
+                "cannot add a bitmap with compression {:?}, only uncompressed bitmaps can be added",
+                compression
+            ));
+        }
+        let info = bitmap.get_info()?;
+        let pixel_type = info.get_pixel_type()?;
+        let width = info.get_width() as usize;
+        let height = info.get_height() as usize;
+        let row_size = width * pixel_type.bytes_per_pixel();
+        let data = bitmap.rows()?.flatten().copied().collect::<Vec<_>>();
+        let mut add_sub_block_info = AddSubBlockInfo::new(
+            coordinate,
+            m_index,
+            x,
+            y,
+            width as i32,
+            height as i32,
+            width as i32,
+            height as i32,
+            pixel_type,
+            compression as i32,
+            &[],
+            &[],
+            &[],
+        );
+        add_sub_block_info.set_data_owned(data);
+        add_sub_block_info.set_stride(row_size as u32);
+        self.add_sub_block(add_sub_block_info)
+    }
+
+    /// Copy the XML-metadata of the document opened by reader to this writer. Note that the metadata is copied as is,
+    /// so it may be inconsistent with the sub-blocks actually written, for instance when only some of the channels are
+    /// copied, use [InitializedCziWriter::copy_metadata_from_with] to update it.
+    pub fn copy_metadata_from(&self, reader: &CziReader) -> Result<()> {
+        self.copy_metadata_from_with(reader, Ok)
+    }
+
+    /// Copy the XML-metadata of the document opened by reader to this writer, after passing it through rewrite.
+    pub fn copy_metadata_from_with<F: FnOnce(String) -> Result<String>>(
+        &self,
+        reader: &CziReader,
+        rewrite: F,
+    ) -> Result<()> {
+        let xml = reader.get_metadata_xml_string()?;
+        self.write_metadata(WriteMetadataInfo::new(rewrite(xml)?.as_bytes()))
     }
 
-    /// Release the specified writer object.
+    /// Finalizes the CZI (i.e. writes out the final directory-segments) and closes the file.
+    /// Note that this method must be called explicitly in order to get a valid CZI - calling 'libCZI_ReleaseWriter' without
+    /// a prior call to this method will close the file immediately without finalization. Dropping an initialized writer
+    /// will also finalize the CZI, but any error is then ignored.
     ///
-    /// \\param  writer_object Handle to the writer object that is to be released.
+    /// \\param  writer_object   Handle to the writer object that is to be closed.
     ///
     /// \\returns    An error-code indicating success or failure of the operation.
-    pub fn release(&self) -> Result<()> {
-        LibCZIApiError::try_from(unsafe { libCZI_ReleaseWriter(**self) })?;
+    pub fn close(self) -> Result<()> {
+        // take out the writer, so that it is released, but not closed again
+        let writer = unsafe { ptr::read(&ManuallyDrop::new(self).writer) };
+        LibCZIApiError::try_from(unsafe { libCZI_WriterClose(*writer) })?;
         Ok(())
     }
 }
 
-impl Drop for CziWriter {
-    fn drop(&mut self) {
-        self.close().ok();
-        self.release().ok();
-    }
-}
-
 impl SingleChannelScalingTileAccessor {
     /// Gets the size information of the specified tile accessor based on the region of interest and zoom factor.
     ///
@@ -1071,8 +2470,7 @@ impl Drop for SingleChannelScalingTileAccessor {
 impl DisplaySettings {
     /// Given a display-settings object and the channel-number, this function fills out the
     /// composition-channel-information which is needed for the multi-channel-composition.
-    /// Note that in the returned 'CompositionChannelInfoInterop' structure, the 'lut' field is a pointer to the LUT-data,
-    /// which must be freed with 'libCZI_Free' by the caller.
+    /// The LUT-data which libCZI allocates is copied into the returned 'CompositionChannelInfo' and freed.
     ///
     /// \\param          display_settings_handle             The display settings handle.
     /// \\param          channel_index                       The channel-index (referring to the display settings object) we are concerned with.
@@ -1095,9 +2493,43 @@ impl DisplaySettings {
                 ptr,
             )
         })?;
-        Ok(unsafe { CompositionChannelInfo::assume_init(composition_channel_info) })
+        Ok(unsafe { CompositionChannelInfo::from_libczi(composition_channel_info.assume_init()) })
+    }
+
+    /// The number of channels with display settings. libCZI has no function to query this, so channel ids are tried
+    /// from 0 up until libCZI reports that there are no display settings for one. This assumes the channel ids are
+    /// contiguous from 0, channels after a gap in the ids are not counted. Other errors are returned.
+    pub fn channel_count(&self) -> Result<i32> {
+        let mut channel_id = 0;
+        loop {
+            match self.get_channel_display_settings(channel_id) {
+                Ok(_) => channel_id += 1,
+                Err(e)
+                    if matches!(
+                        e.downcast_ref::<LibCZIApiError>(),
+                        Some(LibCZIApiError::InvalidArgument | LibCZIApiError::IndexOutOfRange)
+                    ) =>
+                {
+                    return Ok(channel_id);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Iterate over the display settings of all channels (see [DisplaySettings::channel_count] for the assumption
+    /// made about the channel ids), together with their channel ids.
+    pub fn channels(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<(i32, ChannelDisplaySettings)>> + '_> {
+        Ok((0..self.channel_count()?).map(move |channel_id| {
+            Ok((channel_id, self.get_channel_display_settings(channel_id)?))
+        }))
     }
 
+    /// Get the display settings of a channel. libCZI has no functions to query these, use
+    /// [DisplaySettings::compositor_fill_out_composition_channel_info_interop] to read the weight, tinting color,
+    /// black and white point and gradation curve of a channel.
     pub fn get_channel_display_settings(&self, channel_id: i32) -> Result<ChannelDisplaySettings> {
         let mut channel_display_setting = MaybeUninit::uninit();
         let ptr = channel_display_setting.as_mut_ptr();
@@ -1134,17 +2566,35 @@ impl Drop for DisplaySettings {
 /// \\param \[out\] bitmap_object      The resulting bitmap is put here.
 ///
 /// \\return     An error-code indicating success or failure of the operation.
+///
+/// The number of channels is the length of source_bitmaps, channel_info must have an entry for each source bitmap,
+/// otherwise an error is returned. The source bitmaps are only borrowed, they remain valid after the composition and
+/// are released by the caller.
 pub fn compositor_do_multi_channel_composition(
-    channel_count: i32,
-    source_bitmaps: Vec<Bitmap>,
-    channel_info: CompositionChannelInfo,
+    source_bitmaps: &[Bitmap],
+    channel_info: &[CompositionChannelInfo],
 ) -> Result<Bitmap> {
+    if source_bitmaps.len() != channel_info.len() {
+        return Err(anyhow!(
+            "{} source bitmaps were given, but {} channel infos",
+            source_bitmaps.len(),
+            channel_info.len()
+        ));
+    }
+    let source_bitmaps = source_bitmaps
+        .iter()
+        .map(|bitmap| **bitmap)
+        .collect::<Vec<BitmapObjectHandle>>();
+    let channel_info = channel_info
+        .iter()
+        .map(|channel_info| channel_info.0)
+        .collect::<Vec<CompositionChannelInfoInterop>>();
     let mut bitmap = MaybeUninit::uninit();
     let ptr = bitmap.as_mut_ptr();
     LibCZIApiError::try_from(unsafe {
         libCZI_CompositorDoMultiChannelComposition(
-            channel_count,
-            source_bitmaps.as_ptr() as *const BitmapObjectHandle,
+            source_bitmaps.len() as i32,
+            source_bitmaps.as_ptr(),
             channel_info.as_ptr(),
             ptr,
         )
@@ -1159,7 +2609,7 @@ impl ChannelDisplaySettings {
     ///
     /// \\returns    An error-code indicating success or failure of the operation.
     pub fn release(&self) -> Result<()> {
-        LibCZIApiError::try_from(unsafe { libCZI_ReleaseDisplaySettings(**self) })?;
+        LibCZIApiError::try_from(unsafe { libCZI_ReleaseChannelDisplaySettings(**self) })?;
         Ok(())
     }
 }
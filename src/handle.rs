@@ -2,14 +2,33 @@ use crate::misc::Ptr;
 use crate::sys::*;
 use std::mem::MaybeUninit;
 use std::ops::Deref;
+use std::sync::OnceLock;
 
-/// CZI-reader object.
+// The handles are plain integers that libCZIApi maps to its objects, so all handle types are Send and Sync without
+// any unsafe impls. libCZI's reader and input streams can be used from several threads at once, so an opened reader
+// can be shared between threads. A writer and a locked bitmap should only be used from one thread at a time, which
+// InitializedCziWriter and LockedBitmap enforce by not being Sync.
+
+/// The value of an invalid object handle, as libCZI returns when no object is present. libCZI defines it as a
+/// constant in its header only, so it cannot be linked to.
+pub(crate) const INVALID_OBJECT_HANDLE: ObjectHandle = 0;
+
+/// CZI-reader object. Besides the handle, it holds the start of the file read when the document is opened by
+/// [CziReader::open_from_path] or [CziReader::open_from_memory] (see [CziReader::has_external_data]) and the limit
+/// on the size of decoded data (see [CziReader::set_max_decode_bytes]).
 #[derive(Clone, Debug)]
-pub struct CziReader(pub(crate) CziReaderObjectHandle);
+pub struct CziReader {
+    pub(crate) handle: CziReaderObjectHandle,
+    pub(crate) file_header: OnceLock<Vec<u8>>,
+    pub(crate) max_decode_bytes: Option<usize>,
+}
 
-/// sub-block object.
+/// sub-block object, with the limit on the size of decoded data of the reader it was read from.
 #[derive(Clone, Debug)]
-pub struct SubBlock(pub(crate) SubBlockObjectHandle);
+pub struct SubBlock {
+    pub(crate) handle: SubBlockObjectHandle,
+    pub(crate) max_decode_bytes: Option<usize>,
+}
 
 /// input stream object.
 #[derive(Clone, Debug)]
@@ -19,12 +38,6 @@ pub struct InputStream(pub(crate) InputStreamObjectHandle);
 #[derive(Clone, Debug)]
 pub struct OutputStream(pub(crate) OutputStreamObjectHandle);
 
-/// memory allocation object - which is a pointer to a memory block, which must be
-/// freed with 'libCZI_Free'.
-/// TODO(JBL): this is not really used so far, should be removed I guess.
-#[derive(Clone, Debug)]
-pub struct MemoryAllocation(pub(crate) MemoryAllocationObjectHandle);
-
 /// bitmap object.
 #[derive(Clone, Debug)]
 pub struct Bitmap(pub(crate) BitmapObjectHandle);
@@ -94,12 +107,78 @@ macro_rules! impl_struct {
   };
 }
 
+impl CziReader {
+    #[allow(dead_code)]
+    pub(crate) fn handle(&self) -> ObjectHandle {
+        self.handle
+    }
+}
+
+impl Ptr for CziReader {
+    type Pointer = CziReaderObjectHandle;
+
+    unsafe fn assume_init(ptr: MaybeUninit<Self::Pointer>) -> Self {
+        Self {
+            handle: unsafe { ptr.assume_init() },
+            file_header: OnceLock::new(),
+            max_decode_bytes: None,
+        }
+    }
+
+    fn as_mut_ptr(&self) -> *mut Self::Pointer {
+        &self.handle as *const _ as *mut _
+    }
+
+    fn as_ptr(&self) -> *const Self::Pointer {
+        &self.handle as *const _ as *const _
+    }
+}
+
+impl Deref for CziReader {
+    type Target = ObjectHandle;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
+
+impl SubBlock {
+    #[allow(dead_code)]
+    pub(crate) fn handle(&self) -> ObjectHandle {
+        self.handle
+    }
+}
+
+impl Ptr for SubBlock {
+    type Pointer = SubBlockObjectHandle;
+
+    unsafe fn assume_init(ptr: MaybeUninit<Self::Pointer>) -> Self {
+        Self {
+            handle: unsafe { ptr.assume_init() },
+            max_decode_bytes: None,
+        }
+    }
+
+    fn as_mut_ptr(&self) -> *mut Self::Pointer {
+        &self.handle as *const _ as *mut _
+    }
+
+    fn as_ptr(&self) -> *const Self::Pointer {
+        &self.handle as *const _ as *const _
+    }
+}
+
+impl Deref for SubBlock {
+    type Target = ObjectHandle;
+
+    fn deref(&self) -> &Self::Target {
+        &self.handle
+    }
+}
+
 impl_struct! {
-  CziReader: CziReader: CziReaderObjectHandle,
-  SubBlock: SubBlock: SubBlockObjectHandle,
   InputStream: InputStream: InputStreamObjectHandle,
   OutputStream: OutputStream: OutputStreamObjectHandle,
-  MemoryAllocation: MemoryAllocation: MemoryAllocationObjectHandle,
   Bitmap: Bitmap: BitmapObjectHandle,
   MetadataSegment: MetadataSegment: MetadataSegmentObjectHandle,
   Attachment: Attachment: AttachmentObjectHandle,
@@ -1,11 +1,11 @@
-use crate::handle::{InputStream, MemoryAllocation};
-use crate::misc::{PixelType, Ptr};
+use crate::handle::InputStream;
+use crate::misc::{CompressionMode, Dimension, LibCZIApiError, PixelType, Ptr};
 use crate::sys::*;
-use anyhow::{Error, Result};
-use std::ffi::{CStr, CString, c_char, c_void};
+use anyhow::{Error, Result, anyhow};
+use std::ffi::{CStr, CString, c_char, c_ulong, c_void};
 use std::fmt::Debug;
-use std::mem;
-use std::mem::{ManuallyDrop, MaybeUninit};
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ptr;
 
 /// This struct contains the version information of the libCZIApi-library. For versioning libCZI, SemVer2 (<https://semver.org/>) is used.
 /// Note that the value of the tweak version number does not have a meaning (as far as SemVer2 is concerned).
@@ -21,7 +21,8 @@ pub struct LibCZIBuildInformation(pub(crate) LibCZIBuildInformationInterop);
 pub struct InputStreamClassInfo(pub(crate) InputStreamClassInfoInterop);
 
 /// This structure gives additional information about an error that occurred in the external stream.
-#[derive(Clone, Debug)]
+/// The error message is allocated with 'libCZI_AllocateMemory' and owned by this struct, it is freed on drop.
+#[derive(Debug)]
 pub struct ExternalStreamErrorInfo(pub(crate) ExternalStreamErrorInfoInterop);
 
 /// This structure contains information about externally provided functions for reading data from an input stream,
@@ -79,10 +80,15 @@ pub struct BoundingBoxes(pub(crate) BoundingBoxesInterop);
 pub struct SubBlockStatistics(pub(crate) SubBlockStatisticsInterop);
 
 /// This structure extends on the basic statistics about an CZI-document, and includes per-scene statistics.
+/// libCZI puts the per-scene bounding boxes in a variable-size array after the structure, they are copied into a Vec.
 #[derive(Debug)]
-pub struct SubBlockStatisticsEx(pub(crate) SubBlockStatisticsInteropEx);
+pub struct SubBlockStatisticsEx(
+    pub(crate) SubBlockStatisticsInteropEx,
+    pub(crate) Vec<BoundingBoxes>,
+);
 
-#[derive(Clone, Debug)]
+/// The XML metadata of a CZI-document, the memory holding it is owned by this struct and freed when it is dropped.
+#[derive(Debug)]
 pub struct MetadataAsXml(pub(crate) MetadataAsXmlInterop);
 
 /// Information about the bitmap represented by a bitmap-object.
@@ -111,8 +117,13 @@ pub struct AttachmentInfo(pub(crate) AttachmentInfoInterop);
 pub struct FileHeaderInfo(pub(crate) FileHeaderInfoInterop);
 
 /// This structure is used to pass the subblock information to libCZIAPI, describing a subblock to be added to a CZI-file.
-#[derive(Clone, Debug)]
-pub struct AddSubBlockInfo(pub(crate) AddSubBlockInfoInterop);
+/// The writer does _not_ perform any compression: if the compression mode denotes a compressed format, then the data
+/// passed in must already be compressed in that format. For uncompressed data the stride must be set (see 'set_stride'),
+/// the line-size of the bitmap is then determined by the pixel type and the physical width.
+///
+/// The data, metadata and attachment buffers are owned by this struct, the interop struct points to them.
+#[derive(Debug)]
+pub struct AddSubBlockInfo(pub(crate) AddSubBlockInfoInterop, Vec<u8>, Vec<u8>, Vec<u8>);
 
 /// This structure is used to pass the attachment information to libCZIAPI, describing an attachment to be added to a CZI-file.
 #[derive(Clone, Debug)]
@@ -123,12 +134,22 @@ pub struct AddAttachmentInfo(pub(crate) AddAttachmentInfoInterop);
 pub struct WriteMetadataInfo(pub(crate) WriteMetadataInfoInterop);
 
 /// This structure is used to pass the accessor options to libCZIAPI.
-#[derive(Clone, Debug)]
-pub struct AccessorOptions(pub(crate) AccessorOptionsInterop);
+///
+/// The background color (`back_ground_color_r`, `back_ground_color_g` and `back_ground_color_b`) is used to fill the
+/// parts of the destination bitmap not covered by any sub-block, e.g. when the ROI extends past the document. The
+/// components are normalized to 0-1 for integer pixel types and used as-is for float pixel types, gray pixel types
+/// are filled from the color components. If any component is NaN, the destination bitmap is not cleared at all.
+/// Use [AccessorOptions::set_fill_value] to set the background for a specific pixel type.
+///
+/// The additional parameters string is owned by this struct, the interop struct points to it.
+#[derive(Debug)]
+pub struct AccessorOptions(pub(crate) AccessorOptionsInterop, CString);
 
 /// This structure gathers all information about a channel for the purpose of multi-channel-composition.
-#[derive(Clone, Debug)]
-pub struct CompositionChannelInfo(pub(crate) CompositionChannelInfoInterop);
+///
+/// The look-up table is owned by this struct, the interop struct points to it.
+#[derive(Debug)]
+pub struct CompositionChannelInfo(pub(crate) CompositionChannelInfoInterop, Vec<u8>);
 
 /// This structure gathers the information about the scaling.
 #[derive(Clone, Debug)]
@@ -172,21 +193,77 @@ impl_ptr! {
     Coordinate: Coordinate: CoordinateInterop,
     BoundingBoxes: BoundingBoxes: BoundingBoxesInterop,
     SubBlockStatistics: SubBlockStatistics: SubBlockStatisticsInterop,
-    SubBlockStatisticsEx: SubBlockStatisticsEx: SubBlockStatisticsInteropEx,
     MetadataAsXml: MetadataAsXml: MetadataAsXmlInterop,
     BitmapInfo: BitmapInfo: BitmapInfoInterop,
     BitmapLockInfo: BitmapLockInfo: BitmapLockInfoInterop,
     SubBlockInfo: SubBlockInfo: SubBlockInfoInterop,
     AttachmentInfo: AttachmentInfo: AttachmentInfoInterop,
     FileHeaderInfo: FileHeaderInfo: FileHeaderInfoInterop,
-    AddSubBlockInfo: AddSubBlockInfo: AddSubBlockInfoInterop,
     AddAttachmentInfo: AddAttachmentInfo: AddAttachmentInfoInterop,
     WriteMetadataInfo: WriteMetadataInfo: WriteMetadataInfoInterop,
-    AccessorOptions: AccessorOptions: AccessorOptionsInterop,
-    CompositionChannelInfo: CompositionChannelInfo: CompositionChannelInfoInterop,
     ScalingInfo: ScalingInfo: ScalingInfoInterop,
 }
 
+impl Ptr for AccessorOptions {
+    type Pointer = AccessorOptionsInterop;
+
+    /// the additional parameters are copied into a string owned by the new struct
+    unsafe fn assume_init(ptr: MaybeUninit<Self::Pointer>) -> Self {
+        let accessor_options = unsafe { ptr.assume_init() };
+        let additional_parameters = if accessor_options.additional_parameters.is_null() {
+            CString::default()
+        } else {
+            unsafe { CStr::from_ptr(accessor_options.additional_parameters) }.to_owned()
+        };
+        Self::with_additional_parameters(accessor_options, additional_parameters)
+    }
+
+    fn as_mut_ptr(&self) -> *mut Self::Pointer {
+        &self.0 as *const _ as *mut _
+    }
+
+    fn as_ptr(&self) -> *const Self::Pointer {
+        &self.0 as *const _ as *const _
+    }
+}
+
+impl Ptr for AddSubBlockInfo {
+    type Pointer = AddSubBlockInfoInterop;
+
+    /// the data, metadata and attachment are copied into buffers owned by the new struct
+    unsafe fn assume_init(ptr: MaybeUninit<Self::Pointer>) -> Self {
+        let add_sub_block_info = unsafe { ptr.assume_init() };
+        Self::with_buffers(
+            add_sub_block_info,
+            copy_from_raw(add_sub_block_info.data, add_sub_block_info.size_data),
+            copy_from_raw(
+                add_sub_block_info.metadata,
+                add_sub_block_info.size_metadata,
+            ),
+            copy_from_raw(
+                add_sub_block_info.attachment,
+                add_sub_block_info.size_attachment,
+            ),
+        )
+    }
+
+    fn as_mut_ptr(&self) -> *mut Self::Pointer {
+        &self.0 as *const _ as *mut _
+    }
+
+    fn as_ptr(&self) -> *const Self::Pointer {
+        &self.0 as *const _ as *const _
+    }
+}
+
+/// copy size bytes at ptr into a new Vec without taking ownership of the memory, an empty Vec for a null pointer
+fn copy_from_raw(ptr: *const c_void, size: u32) -> Vec<u8> {
+    if ptr.is_null() || size == 0 {
+        return Vec::new();
+    }
+    unsafe { std::slice::from_raw_parts(ptr as *const u8, size as usize) }.to_vec()
+}
+
 impl LibCZIVersionInfo {
     pub fn get_major(&self) -> i32 {
         self.0.major
@@ -247,12 +324,49 @@ impl Drop for InputStreamClassInfo {
 }
 
 impl ExternalStreamErrorInfo {
+    /// Create error information with the message copied into memory allocated with 'libCZI_AllocateMemory',
+    /// as libCZI expects.
+    pub fn new<S: AsRef<str>>(error_code: i32, error_message: S) -> Result<Self> {
+        let error_message = CString::new(error_message.as_ref())?;
+        let bytes = error_message.as_bytes_with_nul();
+        let mut ptr: *mut c_void = std::ptr::null_mut();
+        LibCZIApiError::try_from(unsafe {
+            libCZI_AllocateMemory(bytes.len() as c_ulong, &mut ptr)
+        })?;
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr as *mut u8, bytes.len()) };
+        Ok(Self(ExternalStreamErrorInfoInterop {
+            error_code,
+            error_message: ptr as MemoryAllocationObjectHandle,
+        }))
+    }
+
     pub fn get_error_code(&self) -> i32 {
         self.0.error_code
     }
 
-    pub fn get_error_message(&self) -> MemoryAllocation {
-        MemoryAllocation(self.0.error_message)
+    /// a copy of the error message, empty if there is no message
+    pub fn get_error_message(&self) -> Result<String> {
+        if self.0.error_message == 0 {
+            return Ok(String::new());
+        }
+        Ok(
+            unsafe { CStr::from_ptr(self.0.error_message as *const c_char) }
+                .to_str()?
+                .to_string(),
+        )
+    }
+
+    /// Give up ownership of the error message, for passing the error information to libCZI, which then frees it.
+    pub fn into_raw(self) -> ExternalStreamErrorInfoInterop {
+        ManuallyDrop::new(self).0
+    }
+}
+
+impl Drop for ExternalStreamErrorInfo {
+    fn drop(&mut self) {
+        if self.0.error_message != 0 {
+            unsafe { libCZI_Free(self.0.error_message as *mut c_void) };
+        }
     }
 }
 
@@ -333,6 +447,22 @@ impl IntRect {
     pub fn set_h(&mut self, h: i32) {
         self.0.h = h;
     }
+    /// whether the point x, y lies inside the rectangle, the right and bottom edges are exclusive
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.0.x && x < self.0.x + self.0.w && y >= self.0.y && y < self.0.y + self.0.h
+    }
+    /// the rectangle covered by both rectangles, None if they do not overlap
+    pub fn intersection(&self, other: &IntRect) -> Option<IntRect> {
+        let x = self.0.x.max(other.0.x);
+        let y = self.0.y.max(other.0.y);
+        let w = (self.0.x + self.0.w).min(other.0.x + other.0.w) - x;
+        let h = (self.0.y + self.0.h).min(other.0.y + other.0.h) - y;
+        (w > 0 && h > 0).then(|| IntRect::new(x, y, w, h))
+    }
+    /// whether the rectangles overlap, touching edges do not count
+    pub fn intersects(&self, other: &IntRect) -> bool {
+        self.intersection(other).is_some()
+    }
 }
 
 impl IntSize {
@@ -379,6 +509,24 @@ impl DimBounds {
     pub fn set_size(&mut self, size: [i32; 9]) {
         self.0.size = size;
     }
+    /// the valid dimensions with their start and size, in the order of the dimensions
+    pub fn iter(&self) -> impl Iterator<Item = (Dimension, i32, i32)> + '_ {
+        // start and size are stored in the order of the valid dimensions
+        Dimension::vec_from_bitflags(self.0.dimensions_valid)
+            .into_iter()
+            .enumerate()
+            .map(|(i, dimension)| (dimension, self.0.start[i], self.0.size[i]))
+    }
+    /// the start and size for the dimension, None if the dimension is not valid in these bounds
+    pub fn get(&self, dimension: Dimension) -> Option<(i32, i32)> {
+        let bit = 1 << (dimension as u32 - 1);
+        if self.0.dimensions_valid & bit == 0 {
+            None
+        } else {
+            let index = (self.0.dimensions_valid & (bit - 1)).count_ones() as usize;
+            Some((self.0.start[index], self.0.size[index]))
+        }
+    }
 }
 
 impl Coordinate {
@@ -388,6 +536,10 @@ impl Coordinate {
             value,
         })
     }
+    /// a builder for a coordinate, which packs the values in the order of the valid dimensions
+    pub fn builder() -> CoordinateBuilder {
+        CoordinateBuilder(Coordinate::new(0, [0; 9]))
+    }
     pub fn get_dimensions_valid(&self) -> u32 {
         self.0.dimensions_valid
     }
@@ -400,6 +552,48 @@ impl Coordinate {
     pub fn set_value(&mut self, value: [i32; 9]) {
         self.0.value = value;
     }
+    /// the value for the dimension, None if the dimension is not valid in this coordinate
+    pub fn get(&self, dimension: Dimension) -> Option<i32> {
+        let bit = 1 << (dimension as u32 - 1);
+        if self.0.dimensions_valid & bit == 0 {
+            None
+        } else {
+            // values are stored in the order of the valid dimensions
+            Some(self.0.value[(self.0.dimensions_valid & (bit - 1)).count_ones() as usize])
+        }
+    }
+    /// set the value for the dimension, making the dimension valid if it was not
+    pub fn set(&mut self, dimension: Dimension, value: i32) {
+        let bit = 1 << (dimension as u32 - 1);
+        let index = (self.0.dimensions_valid & (bit - 1)).count_ones() as usize;
+        if self.0.dimensions_valid & bit == 0 {
+            // make room for the new value, keeping the values in the order of the valid dimensions
+            self.0.value.copy_within(index..8, index + 1);
+            self.0.dimensions_valid |= bit;
+        }
+        self.0.value[index] = value;
+    }
+    /// whether every dimension valid in this coordinate is also valid in other and has the same value there
+    pub fn matches(&self, other: &Coordinate) -> bool {
+        Dimension::vec_from_bitflags(self.0.dimensions_valid)
+            .into_iter()
+            .all(|dimension| self.get(dimension.clone()) == other.get(dimension))
+    }
+}
+
+/// builder for a [Coordinate], see [Coordinate::builder]
+#[derive(Clone, Debug)]
+pub struct CoordinateBuilder(Coordinate);
+
+impl CoordinateBuilder {
+    /// set the value for the dimension, the dimensions can be set in any order
+    pub fn set(mut self, dimension: Dimension, value: i32) -> Self {
+        self.0.set(dimension, value);
+        self
+    }
+    pub fn build(self) -> Coordinate {
+        self.0
+    }
 }
 
 impl BoundingBoxes {
@@ -490,6 +684,25 @@ impl SubBlockStatistics {
     }
 }
 
+impl Clone for SubBlockStatisticsEx {
+    fn clone(&self) -> Self {
+        Self(
+            SubBlockStatisticsInteropEx {
+                sub_block_count: self.0.sub_block_count,
+                min_m_index: self.0.min_m_index,
+                max_m_index: self.0.max_m_index,
+                bounding_box: self.0.bounding_box,
+                bounding_box_layer0: self.0.bounding_box_layer0,
+                dim_bounds: self.0.dim_bounds,
+                number_of_per_scenes_bounding_boxes: self.0.number_of_per_scenes_bounding_boxes,
+                // the bounding boxes are not stored in the structure itself, but in the Vec
+                per_scenes_bounding_boxes: __IncompleteArrayField::new(),
+            },
+            self.1.clone(),
+        )
+    }
+}
+
 impl SubBlockStatisticsEx {
     // pub fn new(
     //     sub_block_count: i32,
@@ -532,9 +745,23 @@ impl SubBlockStatisticsEx {
     pub fn get_number_of_per_scenes_bounding_boxes(&self) -> i32 {
         self.0.number_of_per_scenes_bounding_boxes
     }
-    // pub fn get_per_scenes_bounding_boxes(&self) -> Vec<BoundingBoxes> {
-    //     unsafe { transmute(&self.0.per_scenes_bounding_boxes) }.clone()
-    // }
+    /// The per-scene bounding boxes which libCZI put into the statistics. These can be fewer than the number of
+    /// scenes in the document if the statistics were queried with too small a buffer, use
+    /// [crate::CziReader::get_statistics_ex_all] to get all of them.
+    pub fn get_per_scenes_bounding_boxes(&self) -> &[BoundingBoxes] {
+        &self.1
+    }
+    /// the statistics without the per-scene bounding boxes, as returned by [crate::CziReader::get_statistics_simple]
+    pub fn get_statistics_simple(&self) -> SubBlockStatistics {
+        SubBlockStatistics::new(
+            self.0.sub_block_count,
+            self.0.min_m_index,
+            self.0.max_m_index,
+            self.get_bounding_box(),
+            self.get_bounding_box_layer0(),
+            self.get_dim_bounds(),
+        )
+    }
     pub fn set_sub_block_count(&mut self, sub_block_count: i32) {
         self.0.sub_block_count = sub_block_count;
     }
@@ -566,22 +793,21 @@ impl SubBlockStatisticsEx {
 }
 
 impl MetadataAsXml {
+    /// a copy of the XML metadata as a string
     pub fn get_data(&self) -> Result<String> {
-        let xml_data = unsafe {
-            Vec::from_raw_parts(
-                self.0.data as *mut u8,
-                self.0.size as usize,
-                self.0.size as usize,
-            )
-        };
-        Ok(String::from_utf8(xml_data)?)
+        if self.0.data.is_null() {
+            return Ok(String::new());
+        }
+        let xml_data =
+            unsafe { std::slice::from_raw_parts(self.0.data as *const u8, self.0.size as usize) };
+        Ok(String::from_utf8(xml_data.to_vec())?)
     }
 }
 
 impl Drop for MetadataAsXml {
     fn drop(&mut self) {
-        unsafe {
-            libCZI_Free(Box::into_raw(Box::new(self.0.data)) as *mut c_void);
+        if !self.0.data.is_null() {
+            unsafe { libCZI_Free(self.0.data) };
         }
     }
 }
@@ -623,17 +849,14 @@ impl BitmapInfo {
 }
 
 impl BitmapLockInfo {
+    /// a copy of the locked pixel data, the memory itself is owned by the bitmap and is only valid while it is locked,
+    /// use LockedBitmap::data to borrow it instead
     pub fn get_data_roi(&self) -> Vec<u8> {
-        let vec = unsafe {
-            Vec::from_raw_parts(
-                self.0.ptrDataRoi as *mut u8,
-                self.0.size as usize,
-                self.0.size as usize,
-            )
-        };
-        let res = vec.clone();
-        mem::forget(vec);
-        res
+        if self.0.ptrDataRoi.is_null() {
+            return Vec::new();
+        }
+        unsafe { std::slice::from_raw_parts(self.0.ptrDataRoi as *const u8, self.data_len()) }
+            .to_vec()
     }
 
     pub fn get_stride(&self) -> u32 {
@@ -643,6 +866,11 @@ impl BitmapLockInfo {
     pub fn get_size(&self) -> u64 {
         self.0.size
     }
+
+    /// length in bytes of the locked pixel data, the same as get_size, but as usize
+    pub fn data_len(&self) -> usize {
+        self.0.size as usize
+    }
 }
 
 impl SubBlockInfo {
@@ -666,6 +894,9 @@ impl SubBlockInfo {
     pub fn get_compression_mode_raw(&self) -> i32 {
         self.0.compression_mode_raw
     }
+    pub fn get_compression_mode(&self) -> Result<CompressionMode> {
+        CompressionMode::try_from(self.0.compression_mode_raw)
+    }
     pub fn get_pixel_type(&self) -> Result<PixelType> {
         PixelType::try_from(self.0.pixel_type)
     }
@@ -681,9 +912,35 @@ impl SubBlockInfo {
     pub fn get_m_index(&self) -> i32 {
         self.0.m_index
     }
+    /// Ratio of the logical width to the physical width of the sub-block. A factor of 1 means the sub-block is
+    /// stored at full resolution, a factor > 1 means the sub-block is a pyramid tile: its bitmap is smaller than the
+    /// area it covers in logical space and has to be scaled up by this factor to be placed there. None if the
+    /// physical width is not positive.
+    pub fn downsample_factor(&self) -> Option<f64> {
+        (self.0.physical_size.w > 0)
+            .then(|| self.0.logical_rect.w as f64 / self.0.physical_size.w as f64)
+    }
+    /// Whether the sub-block is stored at full resolution, i.e. on pyramid-layer 0: its logical and physical width
+    /// are equal.
+    pub fn is_full_resolution(&self) -> bool {
+        self.0.logical_rect.w == self.0.physical_size.w
+    }
+    /// Size in bytes of the pixel data of the sub-block after decompression. An error is returned if the physical
+    /// size is negative or the size does not fit in a usize.
+    pub fn decompressed_size(&self) -> Result<usize> {
+        let w = usize::try_from(self.0.physical_size.w)?;
+        let h = usize::try_from(self.0.physical_size.h)?;
+        let bytes_per_pixel = self.get_pixel_type()?.bytes_per_pixel();
+        w.checked_mul(h)
+            .and_then(|size| size.checked_mul(bytes_per_pixel))
+            .ok_or_else(|| anyhow!("size of sub-block of {}x{} pixels overflows", w, h))
+    }
     pub fn set_compression_mode_raw(&mut self, compression_mode_raw: i32) {
         self.0.compression_mode_raw = compression_mode_raw
     }
+    pub fn set_compression_mode(&mut self, compression_mode: CompressionMode) {
+        self.0.compression_mode_raw = compression_mode as i32
+    }
     pub fn set_pixel_type(&mut self, pixel_type: PixelType) {
         self.0.pixel_type = pixel_type as i32;
     }
@@ -701,13 +958,54 @@ impl SubBlockInfo {
     }
 }
 
+/// Format a GUID as stored by libCZI in the canonical hyphenated form. The first three fields are stored in
+/// little-endian byte order, the last two as bytes.
+fn guid_to_string(guid: &[u8; 16]) -> String {
+    format!(
+        "{:08x}-{:04x}-{:04x}-{}-{}",
+        u32::from_le_bytes([guid[0], guid[1], guid[2], guid[3]]),
+        u16::from_le_bytes([guid[4], guid[5]]),
+        u16::from_le_bytes([guid[6], guid[7]]),
+        guid[8..10]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>(),
+        guid[10..]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>()
+    )
+}
+
+/// The content file type up to the first zero, all of it if it has no zero.
+fn content_file_type_str(content_file_type: &[u8]) -> Result<&str> {
+    let length = content_file_type
+        .iter()
+        .position(|b| *b == 0)
+        .unwrap_or(content_file_type.len());
+    Ok(std::str::from_utf8(&content_file_type[..length])?)
+}
+
 impl AttachmentInfo {
     pub fn get_guid(&self) -> [u8; 16] {
         self.0.guid
     }
+    /// the GUID as a uuid, taking the byte order used by libCZI into account
+    #[cfg(feature = "uuid")]
+    pub fn get_guid_uuid(&self) -> uuid::Uuid {
+        uuid::Uuid::from_bytes_le(self.0.guid)
+    }
+    /// the GUID in the canonical hyphenated form, for instance \"123e4567-e89b-12d3-a456-426614174000\"
+    pub fn get_guid_string(&self) -> String {
+        guid_to_string(&self.0.guid)
+    }
     pub fn get_content_file_type(&self) -> [u8; 9] {
         self.0.content_file_type
     }
+    /// the content file type as a string, for instance \"JPG\", without the zero padding
+    pub fn content_file_type_str(&self) -> Result<&str> {
+        content_file_type_str(&self.0.content_file_type)
+    }
     pub fn get_name(&self) -> Result<String> {
         Ok(
             CStr::from_bytes_until_nul(&self.0.name.iter().map(|&i| i as u8).collect::<Vec<_>>())?
@@ -746,6 +1044,15 @@ impl FileHeaderInfo {
     pub fn get_guid(&self) -> [u8; 16] {
         self.0.guid
     }
+    /// the file-GUID as a uuid, taking the byte order used by libCZI into account
+    #[cfg(feature = "uuid")]
+    pub fn get_guid_uuid(&self) -> uuid::Uuid {
+        uuid::Uuid::from_bytes_le(self.0.guid)
+    }
+    /// the file-GUID in the canonical hyphenated form, for instance \"123e4567-e89b-12d3-a456-426614174000\"
+    pub fn get_guid_string(&self) -> String {
+        guid_to_string(&self.0.guid)
+    }
     pub fn get_major_version(&self) -> i32 {
         self.0.majorVersion
     }
@@ -763,12 +1070,18 @@ impl FileHeaderInfo {
     }
 }
 
+impl Clone for AddSubBlockInfo {
+    fn clone(&self) -> Self {
+        Self::with_buffers(self.0, self.1.clone(), self.2.clone(), self.3.clone())
+    }
+}
+
 impl AddSubBlockInfo {
     #[allow(clippy::too_many_arguments)]
+    /// m_index should be given for the tiles of a mosaic and omitted (None) otherwise
     pub fn new(
         coordinate: Coordinate,
-        m_index_valid: u8,
-        m_index: i32,
+        m_index: Option<i32>,
         x: i32,
         y: i32,
         logical_width: i32,
@@ -781,39 +1094,52 @@ impl AddSubBlockInfo {
         metadata: &[u8],
         attachment: &[u8],
     ) -> Self {
-        let data = ManuallyDrop::new(data.to_vec());
-        let metadata = ManuallyDrop::new(metadata.to_vec());
-        let attachment = ManuallyDrop::new(attachment.to_vec());
-
-        Self(AddSubBlockInfoInterop {
-            coordinate: coordinate.0,
-            m_index_valid,
-            m_index,
-            x,
-            y,
-            logical_width,
-            logical_height,
-            physical_width,
-            physical_height,
-            pixel_type: pixel_type as i32,
-            compression_mode_raw,
-            size_data: data.len() as u32,
-            data: data.as_ptr() as *const c_void,
-            stride: 1,
-            size_metadata: metadata.len() as u32,
-            metadata: metadata.as_ptr() as *const c_void,
-            size_attachment: attachment.len() as u32,
-            attachment: attachment.as_ptr() as *const c_void,
-        })
+        Self::with_buffers(
+            AddSubBlockInfoInterop {
+                coordinate: coordinate.0,
+                m_index_valid: m_index.is_some() as u8,
+                m_index: m_index.unwrap_or_default(),
+                x,
+                y,
+                logical_width,
+                logical_height,
+                physical_width,
+                physical_height,
+                pixel_type: pixel_type as i32,
+                compression_mode_raw,
+                size_data: 0,
+                data: ptr::null(),
+                stride: 1,
+                size_metadata: 0,
+                metadata: ptr::null(),
+                size_attachment: 0,
+                attachment: ptr::null(),
+            },
+            data.to_vec(),
+            metadata.to_vec(),
+            attachment.to_vec(),
+        )
+    }
+    /// point the interop struct to the owned data, metadata and attachment buffers
+    fn with_buffers(
+        mut add_sub_block_info: AddSubBlockInfoInterop,
+        data: Vec<u8>,
+        metadata: Vec<u8>,
+        attachment: Vec<u8>,
+    ) -> Self {
+        add_sub_block_info.size_data = data.len() as u32;
+        add_sub_block_info.data = data.as_ptr() as *const c_void;
+        add_sub_block_info.size_metadata = metadata.len() as u32;
+        add_sub_block_info.metadata = metadata.as_ptr() as *const c_void;
+        add_sub_block_info.size_attachment = attachment.len() as u32;
+        add_sub_block_info.attachment = attachment.as_ptr() as *const c_void;
+        Self(add_sub_block_info, data, metadata, attachment)
     }
     pub fn get_coordinate(&self) -> Coordinate {
         Coordinate(self.0.coordinate)
     }
-    pub fn get_m_index_valid(&self) -> u8 {
-        self.0.m_index_valid
-    }
-    pub fn get_m_index(&self) -> i32 {
-        self.0.m_index
+    pub fn get_m_index(&self) -> Option<i32> {
+        (self.0.m_index_valid != 0).then_some(self.0.m_index)
     }
     pub fn get_x(&self) -> i32 {
         self.0.x
@@ -839,50 +1165,36 @@ impl AddSubBlockInfo {
     pub fn get_compression_mode_raw(&self) -> i32 {
         self.0.compression_mode_raw
     }
+    pub fn get_compression_mode(&self) -> Result<CompressionMode> {
+        CompressionMode::try_from(self.0.compression_mode_raw)
+    }
+    pub fn get_stride(&self) -> u32 {
+        self.0.stride
+    }
     pub fn get_size_data(&self) -> u32 {
         self.0.size_data
     }
     pub fn get_data(&self) -> Vec<u8> {
-        unsafe {
-            Vec::from_raw_parts(
-                self.0.data as *mut u8,
-                self.0.size_data as usize,
-                self.0.size_data as usize,
-            )
-        }
+        self.1.clone()
     }
     pub fn get_size_metadata(&self) -> u32 {
         self.0.size_metadata
     }
     pub fn get_metadata(&self) -> Vec<u8> {
-        unsafe {
-            Vec::from_raw_parts(
-                self.0.metadata as *mut u8,
-                self.0.size_metadata as usize,
-                self.0.size_metadata as usize,
-            )
-        }
+        self.2.clone()
     }
     pub fn get_size_attachment(&self) -> u32 {
         self.0.size_attachment
     }
     pub fn get_attachment(&self) -> Vec<u8> {
-        unsafe {
-            Vec::from_raw_parts(
-                self.0.attachment as *mut u8,
-                self.0.attachment as usize,
-                self.0.attachment as usize,
-            )
-        }
+        self.3.clone()
     }
     pub fn set_coordinate(&mut self, coordinate: Coordinate) {
         self.0.coordinate = coordinate.0
     }
-    pub fn set_m_index_valid(&mut self, m_index_valid: u8) {
-        self.0.m_index_valid = m_index_valid
-    }
-    pub fn set_m_index(&mut self, m_index: i32) {
-        self.0.m_index = m_index
+    pub fn set_m_index(&mut self, m_index: Option<i32>) {
+        self.0.m_index_valid = m_index.is_some() as u8;
+        self.0.m_index = m_index.unwrap_or_default()
     }
     pub fn set_x(&mut self, x: i32) {
         self.0.x = x
@@ -908,20 +1220,28 @@ impl AddSubBlockInfo {
     pub fn set_compression_mode_raw(&mut self, compression_mode_raw: i32) {
         self.0.compression_mode_raw = compression_mode_raw
     }
+    pub fn set_compression_mode(&mut self, compression_mode: CompressionMode) {
+        self.0.compression_mode_raw = compression_mode as i32
+    }
+    /// Only used if the compression mode is 'UnCompressed', in which case it is the stride of the bitmap in bytes.
+    pub fn set_stride(&mut self, stride: u32) {
+        self.0.stride = stride
+    }
     pub fn set_data(&mut self, data: &[u8]) {
-        let data = ManuallyDrop::new(data.to_vec());
-        self.0.data = data.as_ptr() as *const c_void;
-        self.0.size_data = data.len() as u32;
+        self.set_data_owned(data.to_vec());
+    }
+    /// set the data without copying it
+    pub fn set_data_owned(&mut self, data: Vec<u8>) {
+        let (metadata, attachment) = (mem::take(&mut self.2), mem::take(&mut self.3));
+        *self = Self::with_buffers(self.0, data, metadata, attachment);
     }
     pub fn set_metadata(&mut self, metadata: &[u8]) {
-        let metadata = ManuallyDrop::new(metadata.to_vec());
-        self.0.metadata = metadata.as_ptr() as *const c_void;
-        self.0.size_metadata = metadata.len() as u32;
+        let (data, attachment) = (mem::take(&mut self.1), mem::take(&mut self.3));
+        *self = Self::with_buffers(self.0, data, metadata.to_vec(), attachment);
     }
     pub fn set_attachment(&mut self, attachment: &[u8]) {
-        let attachment = ManuallyDrop::new(attachment.to_vec());
-        self.0.attachment = attachment.as_ptr() as *const c_void;
-        self.0.size_attachment = attachment.len() as u32;
+        let (data, metadata) = (mem::take(&mut self.1), mem::take(&mut self.2));
+        *self = Self::with_buffers(self.0, data, metadata, attachment.to_vec());
     }
 }
 
@@ -947,6 +1267,10 @@ impl AddAttachmentInfo {
     pub fn get_content_file_type(&self) -> [u8; 8] {
         self.0.contentFileType
     }
+    /// the content file type as a string, for instance \"JPG\", without the zero padding
+    pub fn content_file_type_str(&self) -> Result<&str> {
+        content_file_type_str(&self.0.contentFileType)
+    }
     pub fn get_name(&self) -> [u8; 80] {
         self.0.name
     }
@@ -954,13 +1278,7 @@ impl AddAttachmentInfo {
         self.0.size_attachment_data
     }
     pub fn get_attachment_data(&self) -> Vec<u8> {
-        unsafe {
-            Vec::from_raw_parts(
-                self.0.attachment_data as *mut u8,
-                self.0.attachment_data as usize,
-                self.0.attachment_data as usize,
-            )
-        }
+        copy_from_raw(self.0.attachment_data, self.0.size_attachment_data)
     }
     pub fn set_guid(&mut self, guid: [u8; 16]) {
         self.0.guid = guid
@@ -989,13 +1307,12 @@ impl WriteMetadataInfo {
     pub fn get_size_metadata(&self) -> u32 {
         self.0.size_metadata
     }
-    pub fn get_metadata(&self) -> Vec<u8> {
-        unsafe {
-            Vec::from_raw_parts(
-                self.0.metadata as *mut u8,
-                self.0.metadata as usize,
-                self.0.metadata as usize,
-            )
+    /// a copy of the metadata, None if there is no metadata
+    pub fn get_metadata(&self) -> Option<Vec<u8>> {
+        if self.0.metadata.is_null() || self.0.size_metadata == 0 {
+            None
+        } else {
+            Some(copy_from_raw(self.0.metadata, self.0.size_metadata))
         }
     }
     pub fn set_metadata(&mut self, metadata: &[u8]) {
@@ -1005,6 +1322,30 @@ impl WriteMetadataInfo {
     }
 }
 
+/// black background, sorted by M-index, no visibility check optimization and no additional parameters
+impl Default for AccessorOptions {
+    fn default() -> Self {
+        Self::with_additional_parameters(
+            AccessorOptionsInterop {
+                back_ground_color_r: 0.0,
+                back_ground_color_g: 0.0,
+                back_ground_color_b: 0.0,
+                sort_by_m: true,
+                use_visibility_check_optimization: false,
+                additional_parameters: ptr::null(),
+            },
+            CString::default(),
+        )
+    }
+}
+
+/// the clone owns a copy of the additional parameters string
+impl Clone for AccessorOptions {
+    fn clone(&self) -> Self {
+        Self::with_additional_parameters(self.0, self.1.clone())
+    }
+}
+
 impl AccessorOptions {
     pub fn new<S: AsRef<str>>(
         back_ground_color_r: f32,
@@ -1014,16 +1355,25 @@ impl AccessorOptions {
         use_visibility_check_optimization: bool,
         additional_parameters: S,
     ) -> Result<Self> {
-        let additional_parameters =
-            ManuallyDrop::new(CString::new(additional_parameters.as_ref())?);
-        Ok(Self(AccessorOptionsInterop {
-            back_ground_color_r,
-            back_ground_color_g,
-            back_ground_color_b,
-            sort_by_m,
-            use_visibility_check_optimization,
-            additional_parameters: additional_parameters.as_ptr(),
-        }))
+        Ok(Self::with_additional_parameters(
+            AccessorOptionsInterop {
+                back_ground_color_r,
+                back_ground_color_g,
+                back_ground_color_b,
+                sort_by_m,
+                use_visibility_check_optimization,
+                additional_parameters: ptr::null(),
+            },
+            CString::new(additional_parameters.as_ref())?,
+        ))
+    }
+    /// point the interop struct to the owned additional parameters string
+    fn with_additional_parameters(
+        mut accessor_options: AccessorOptionsInterop,
+        additional_parameters: CString,
+    ) -> Self {
+        accessor_options.additional_parameters = additional_parameters.as_ptr();
+        Self(accessor_options, additional_parameters)
     }
     pub fn get_background_color_r(&self) -> f32 {
         self.0.back_ground_color_r
@@ -1041,9 +1391,7 @@ impl AccessorOptions {
         self.0.use_visibility_check_optimization
     }
     pub fn get_additional_parameters(&self) -> Result<String> {
-        Ok(unsafe { CStr::from_ptr(self.0.additional_parameters) }
-            .to_str()?
-            .to_string())
+        Ok(self.1.to_str()?.to_string())
     }
     pub fn set_background_color_r(&mut self, back_ground_color_r: f32) {
         self.0.back_ground_color_r = back_ground_color_r
@@ -1067,14 +1415,67 @@ impl AccessorOptions {
         &mut self,
         additional_parameters: S,
     ) -> Result<()> {
-        let additional_parameters =
-            ManuallyDrop::new(CString::new(additional_parameters.as_ref())?);
-        self.0.additional_parameters = additional_parameters.as_ptr();
+        self.1 = CString::new(additional_parameters.as_ref())?;
+        self.0.additional_parameters = self.1.as_ptr();
+        Ok(())
+    }
+    /// set all components of the background color to the same value, i.e. a gray background
+    pub fn set_background_gray(&mut self, value: f32) {
+        self.0.back_ground_color_r = value;
+        self.0.back_ground_color_g = value;
+        self.0.back_ground_color_b = value;
+    }
+    /// do not clear the destination bitmap, pixels not covered by any sub-block are left uninitialized
+    pub fn set_no_background(&mut self) {
+        self.set_background_gray(f32::NAN);
+    }
+    /// whether the destination bitmap will be cleared with the background color
+    pub fn has_background(&self) -> bool {
+        !(self.0.back_ground_color_r.is_nan()
+            || self.0.back_ground_color_g.is_nan()
+            || self.0.back_ground_color_b.is_nan())
+    }
+    /// set the background such that pixels not covered by any sub-block get the value fill_value in a bitmap of
+    /// the given pixel type, for integer pixel types fill_value is scaled to the range 0-1 of the background color,
+    /// for float pixel types it is used as-is, NaN disables clearing of the destination bitmap
+    pub fn set_fill_value(&mut self, pixel_type: PixelType, fill_value: f64) -> Result<()> {
+        let value = match pixel_type {
+            PixelType::Gray8 | PixelType::Bgr24 | PixelType::Bgra32 => fill_value / u8::MAX as f64,
+            PixelType::Gray16 | PixelType::Bgr48 => fill_value / u16::MAX as f64,
+            PixelType::Gray32Float | PixelType::Bgr96Float | PixelType::Gray64Float => fill_value,
+            _ => return Err(anyhow!("No fill value for pixel type {:?}", pixel_type)),
+        };
+        self.set_background_gray(value as f32);
         Ok(())
     }
+    /// the value pixels not covered by any sub-block get in a bitmap of the given pixel type, None if the destination
+    /// bitmap is not cleared, the mean of the color components is used if they differ
+    pub fn get_fill_value(&self, pixel_type: PixelType) -> Result<Option<f64>> {
+        if !self.has_background() {
+            return Ok(None);
+        }
+        let value = (self.0.back_ground_color_r as f64
+            + self.0.back_ground_color_g as f64
+            + self.0.back_ground_color_b as f64)
+            / 3.0;
+        Ok(Some(match pixel_type {
+            PixelType::Gray8 | PixelType::Bgr24 | PixelType::Bgra32 => value * u8::MAX as f64,
+            PixelType::Gray16 | PixelType::Bgr48 => value * u16::MAX as f64,
+            PixelType::Gray32Float | PixelType::Bgr96Float | PixelType::Gray64Float => value,
+            _ => return Err(anyhow!("No fill value for pixel type {:?}", pixel_type)),
+        }))
+    }
+}
+
+impl Clone for CompositionChannelInfo {
+    fn clone(&self) -> Self {
+        Self::with_look_up_table(self.0, self.1.clone())
+    }
 }
 
 impl CompositionChannelInfo {
+    /// Create the channel information, the look-up table is padded with zeros or truncated to
+    /// look_up_table_element_count elements.
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         weight: f32,
@@ -1087,18 +1488,55 @@ impl CompositionChannelInfo {
         look_up_table_element_count: i32,
         look_up_table: &[u8],
     ) -> Self {
-        let mut look_up_table = ManuallyDrop::new(look_up_table.to_vec());
-        Self(CompositionChannelInfoInterop {
-            weight,
-            enable_tinting,
-            tinting_color_r,
-            tinting_color_g,
-            tinting_color_b,
-            black_point,
-            white_point,
-            look_up_table_element_count,
-            ptr_look_up_table: look_up_table.as_mut_ptr(),
-        })
+        let mut look_up_table = look_up_table.to_vec();
+        look_up_table.resize(look_up_table_element_count.max(0) as usize, 0);
+        Self::with_look_up_table(
+            CompositionChannelInfoInterop {
+                weight,
+                enable_tinting,
+                tinting_color_r,
+                tinting_color_g,
+                tinting_color_b,
+                black_point,
+                white_point,
+                look_up_table_element_count,
+                ptr_look_up_table: ptr::null_mut(),
+            },
+            look_up_table,
+        )
+    }
+    /// Take the channel information filled out by libCZI, copying the look-up table which was allocated by libCZI
+    /// and freeing it.
+    ///
+    /// # Safety
+    /// The look-up table pointer of the interop struct must be null or allocated by libCZI with at least
+    /// look_up_table_element_count elements, and must not be used or freed after this call.
+    pub(crate) unsafe fn from_libczi(
+        composition_channel_info: CompositionChannelInfoInterop,
+    ) -> Self {
+        let ptr = composition_channel_info.ptr_look_up_table;
+        let look_up_table = if ptr.is_null() {
+            Vec::new()
+        } else {
+            let len = composition_channel_info.look_up_table_element_count.max(0) as usize;
+            let look_up_table = unsafe { std::slice::from_raw_parts(ptr, len) }.to_vec();
+            unsafe { libCZI_Free(ptr as *mut c_void) };
+            look_up_table
+        };
+        Self::with_look_up_table(composition_channel_info, look_up_table)
+    }
+    /// point the interop struct to the owned look-up table
+    fn with_look_up_table(
+        mut composition_channel_info: CompositionChannelInfoInterop,
+        mut look_up_table: Vec<u8>,
+    ) -> Self {
+        composition_channel_info.look_up_table_element_count = look_up_table.len() as i32;
+        composition_channel_info.ptr_look_up_table = if look_up_table.is_empty() {
+            ptr::null_mut()
+        } else {
+            look_up_table.as_mut_ptr()
+        };
+        Self(composition_channel_info, look_up_table)
     }
     pub fn get_weight(&self) -> f32 {
         self.0.weight
@@ -1115,6 +1553,19 @@ impl CompositionChannelInfo {
     pub fn get_tinting_color_b(&self) -> u8 {
         self.0.tinting_color_b
     }
+    /// the tinting color as (r, g, b), None if tinting is not enabled
+    pub fn tinting_color(&self) -> Option<(u8, u8, u8)> {
+        (self.0.enable_tinting != 0).then_some((
+            self.0.tinting_color_r,
+            self.0.tinting_color_g,
+            self.0.tinting_color_b,
+        ))
+    }
+    /// whether the gradation curve is linear between the black and white point, otherwise the look-up table holds
+    /// the gradation curve, for instance because a gamma is set
+    pub fn is_linear(&self) -> bool {
+        self.1.is_empty()
+    }
     pub fn get_black_point(&self) -> f32 {
         self.0.black_point
     }
@@ -1124,14 +1575,13 @@ impl CompositionChannelInfo {
     pub fn get_look_up_table_element_count(&self) -> i32 {
         self.0.look_up_table_element_count
     }
+    /// the look-up table, borrowed from the channel information
+    pub fn look_up_table(&self) -> &[u8] {
+        &self.1
+    }
+    /// a copy of the look-up table
     pub fn get_look_up_table(&self) -> Vec<u8> {
-        unsafe {
-            Vec::from_raw_parts(
-                self.0.ptr_look_up_table,
-                self.0.look_up_table_element_count as usize,
-                self.0.look_up_table_element_count as usize,
-            )
-        }
+        self.1.clone()
     }
     pub fn set_weight(&mut self, weight: f32) {
         self.0.weight = weight
@@ -1154,13 +1604,14 @@ impl CompositionChannelInfo {
     pub fn set_white_point(&mut self, white_point: f32) {
         self.0.white_point = white_point
     }
+    /// the look-up table is padded with zeros or truncated to look_up_table_element_count elements
     pub fn set_look_up_table_element_count(&mut self, look_up_table_element_count: i32) {
-        self.0.look_up_table_element_count = look_up_table_element_count
+        let mut look_up_table = mem::take(&mut self.1);
+        look_up_table.resize(look_up_table_element_count.max(0) as usize, 0);
+        *self = Self::with_look_up_table(self.0, look_up_table);
     }
     pub fn set_look_up_table(&mut self, look_up_table: &[u8]) {
-        let mut look_up_table = ManuallyDrop::new(look_up_table.to_vec());
-        self.0.ptr_look_up_table = look_up_table.as_mut_ptr();
-        self.0.look_up_table_element_count = look_up_table.len() as i32;
+        *self = Self::with_look_up_table(self.0, look_up_table.to_vec());
     }
 }
 
@@ -1,47 +1,1555 @@
 extern crate link_cplusplus;
 
+#[cfg(feature = "ndarray")]
+mod array;
+#[cfg(feature = "image")]
+mod dynamic_image;
 mod functions;
 mod handle;
 mod interop;
 mod misc;
 pub mod sys;
 
+#[cfg(any(test, feature = "testing"))]
+pub mod fixtures;
+
+#[cfg(feature = "ndarray")]
+pub use array::{BitmapArray, Frame, PixelElement};
 pub use functions::*;
 pub use handle::*;
 pub use interop::*;
-pub use misc::{Dimension, LibCZIApiError, PixelType, RawDataType};
+pub use misc::{
+    CompressionMode, Dimension, DimensionInfo, LibCZIApiError, PixelType, PyramidLayerInfo,
+    PyramidLayerStatistics, PyramidStatistics, RawDataType, WriterInitOptions, is_czi,
+    is_multi_file,
+};
 
 #[cfg(test)]
 mod tests {
-    use crate::handle::{CziReader, InputStream};
-    use crate::interop::{LibCZIBuildInformation, ReaderOpenInfo};
-    use crate::misc::Dimension;
+    use crate::fixtures;
+    use crate::functions::{
+        compositor_do_multi_channel_composition, enumerate_stream_classes, parse_time_stamps,
+        self_test, stream_classes_count, version_report,
+    };
+    use crate::handle::{Bitmap, CziReader, CziWriter, InputStream, OutputStream};
+    use crate::interop::{
+        AccessorOptions, AddAttachmentInfo, AddSubBlockInfo, CompositionChannelInfo, Coordinate,
+        DimBounds, ExternalStreamErrorInfo, IntRect, IntSize, LibCZIBuildInformation,
+        LibCZIVersionInfo, ReaderOpenInfo, SubBlockInfo, WriteMetadataInfo,
+    };
+    use crate::misc::{
+        CompressionMode, Dimension, DimensionInfo, LibCZIApiError, PixelType, PyramidStatistics,
+        RawDataType, WriterInitOptions, is_czi, is_multi_file,
+    };
     use anyhow::{Error, Result};
-    use std::env;
+    use std::fs;
+    use std::io;
     use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    fn open_tiny_czi() -> Result<CziReader> {
+        CziReader::open_from_memory(&fixtures::tiny_czi()?)
+    }
 
     #[test]
-    fn test_read_shape() -> Result<()> {
-        let path = env::home_dir()
-            .unwrap()
-            .join("code/rust/ndbioimage/tests/files/Experiment-2029.czi");
-        assert!(path.exists());
+    fn test_tiny_czi() -> Result<()> {
+        let bytes = fixtures::tiny_czi()?;
+        assert!(bytes.starts_with(b"ZISRAWFILE"));
+        let czi = open_tiny_czi()?;
+        let statistics = czi.get_statistics_simple()?;
+        assert_eq!(
+            statistics.get_sub_block_count(),
+            fixtures::TINY_CZI_CHANNELS
+        );
+        assert_eq!(czi.sub_block_count()?, fixtures::TINY_CZI_CHANNELS);
+        assert_eq!(
+            statistics.get_bounding_box().get_w(),
+            fixtures::TINY_CZI_SIZE
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_attachment_copy_to() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        assert_eq!(czi.get_attachment_count()?, 1);
+        let attachment = czi.read_attachment(0)?;
+        let mut data = Vec::new();
+        let n = attachment.copy_to(&mut data)?;
+        assert_eq!(n as usize, data.len());
+        assert_eq!(data, fixtures::tiny_czi_time_stamps_attachment());
+        Ok(())
+    }
+
+    #[test]
+    fn test_coordinate_get() {
+        // Z and T are valid, values are stored in the order of the valid dimensions
+        let coordinate = Coordinate::new(0b101, [3, 7, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(coordinate.get(Dimension::Z), Some(3));
+        assert_eq!(coordinate.get(Dimension::C), None);
+        assert_eq!(coordinate.get(Dimension::T), Some(7));
+        let query = Coordinate::new(0b100, [7, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(query.matches(&coordinate));
+        assert!(!coordinate.matches(&query));
+    }
+
+    #[test]
+    fn test_add_sub_block_info_m_index() {
+        let coordinate = Coordinate::new(0, [0; 9]);
+        let mut info = AddSubBlockInfo::new(
+            coordinate,
+            None,
+            0,
+            0,
+            1,
+            1,
+            1,
+            1,
+            PixelType::Gray8,
+            0,
+            &[0],
+            &[],
+            &[],
+        );
+        assert_eq!(info.get_m_index(), None);
+        info.set_m_index(Some(3));
+        assert_eq!(info.get_m_index(), Some(3));
+    }
+
+    #[test]
+    fn test_add_sub_block_info_buffers() {
+        let mut info = AddSubBlockInfo::new(
+            Coordinate::new(0, [0; 9]),
+            None,
+            0,
+            0,
+            2,
+            1,
+            2,
+            1,
+            PixelType::Gray8,
+            0,
+            &[1, 2],
+            b"<METADATA/>",
+            &[],
+        );
+        assert_eq!(info.get_data(), [1, 2]);
+        assert_eq!(info.get_metadata(), b"<METADATA/>");
+        assert!(info.get_attachment().is_empty());
+        info.set_attachment(&[3, 4, 5]);
+        assert_eq!(info.get_size_attachment(), 3);
+        assert_eq!(info.get_attachment(), [3, 4, 5]);
+        let clone = info.clone();
+        info.set_data_owned(vec![7; 4]);
+        assert_eq!(info.get_size_data(), 4);
+        assert_eq!(info.get_data(), [7; 4]);
+        assert_eq!(info.get_metadata(), b"<METADATA/>");
+        assert_eq!(clone.get_data(), [1, 2]);
+        assert_eq!(clone.get_attachment(), [3, 4, 5]);
+    }
+
+    #[test]
+    fn test_add_attachment_info_data() {
+        let mut info = AddAttachmentInfo::new([0; 16], [0; 8], [0; 80], &[1, 2, 3]);
+        assert_eq!(info.get_size_attachment_data(), 3);
+        assert_eq!(info.get_attachment_data(), [1, 2, 3]);
+        let data = (0..=255u8).collect::<Vec<_>>();
+        info.set_attachment_data(&data);
+        assert_eq!(info.get_size_attachment_data(), 256);
+        assert_eq!(info.get_attachment_data(), data);
+        info.set_attachment_data(&[]);
+        assert!(info.get_attachment_data().is_empty());
+    }
+
+    #[test]
+    fn test_write_metadata_info() {
+        let mut info = WriteMetadataInfo::new(b"<ImageDocument/>");
+        assert_eq!(info.get_size_metadata(), 16);
+        assert_eq!(info.get_metadata(), Some(b"<ImageDocument/>".to_vec()));
+        info.set_metadata(&[]);
+        assert_eq!(info.get_metadata(), None);
+    }
+
+    #[test]
+    fn test_coordinate_set() {
+        let mut coordinate = Coordinate::new(0b100, [7, 0, 0, 0, 0, 0, 0, 0, 0]);
+        coordinate.set(Dimension::Z, 3);
+        coordinate.set(Dimension::T, 8);
+        assert_eq!(coordinate.get_dimensions_valid(), 0b101);
+        assert_eq!(coordinate.get(Dimension::Z), Some(3));
+        assert_eq!(coordinate.get(Dimension::T), Some(8));
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_read_region_multichannel() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let size = fixtures::TINY_CZI_SIZE;
+        let array = czi.read_region_multichannel::<u8>(
+            Coordinate::new(0, [0; 9]),
+            IntRect::new(0, 0, size, size),
+        )?;
+        assert_eq!(
+            array.dim(),
+            (
+                fixtures::TINY_CZI_CHANNELS as usize,
+                size as usize,
+                size as usize
+            )
+        );
+        for ((c, y, x), value) in array.indexed_iter() {
+            assert_eq!(
+                *value,
+                fixtures::tiny_czi_pixel(c as i32, x as i32, y as i32)
+            );
+        }
+        assert!(
+            czi.read_region_multichannel::<u16>(
+                Coordinate::new(0, [0; 9]),
+                IntRect::new(0, 0, size, size)
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_to_ndarray() -> Result<()> {
+        use crate::BitmapArray;
+
+        let czi = CziReader::open_from_memory(&fixtures::mixed_pixel_type_czi()?)?;
+        let size = fixtures::TINY_CZI_SIZE as usize;
+        for index in 0..czi.sub_block_count()? {
+            match czi.read_sub_block_as_bitmap(index)?.to_ndarray()? {
+                BitmapArray::Gray16(array) => {
+                    assert_eq!(array.dim(), (size, size));
+                    assert_eq!(array[[1, 2]], 64 * (size as u16 + 2));
+                }
+                BitmapArray::Bgr24(array) => {
+                    assert_eq!(array.dim(), (size, size, 3));
+                    let pixel = array.index_axis(ndarray::Axis(0), 1).row(2).to_vec();
+                    assert_eq!(pixel, fixtures::MIXED_PIXEL_TYPE_CZI_BGR);
+                }
+                array => panic!("unexpected array {:?}", array),
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_time_series() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        assert_eq!(
+            czi.time_stamps()?,
+            Some(fixtures::TINY_CZI_TIME_STAMPS.to_vec())
+        );
+        let size = fixtures::TINY_CZI_SIZE;
+        let frames = czi
+            .time_series::<u8>(Coordinate::new(0, [0; 9]), IntRect::new(0, 0, size, size))?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].t, 0);
+        assert_eq!(
+            frames[0].time_stamp,
+            Some(fixtures::TINY_CZI_TIME_STAMPS[0])
+        );
+        assert_eq!(frames[0].data.dim().0, fixtures::TINY_CZI_CHANNELS as usize);
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_czi() -> Result<()> {
+        let bytes = fixtures::tiny_czi()?;
+        assert!(is_czi(&bytes));
+        assert!(is_czi(&bytes[..32]));
+        assert!(!is_czi(&bytes[..31]));
+        assert!(!is_czi(b"ZISRAWSUBBLK\0\0\0\0"));
+        let mut bogus = bytes[..32].to_vec();
+        bogus[..10].copy_from_slice(b"ZISRAWFILF");
+        assert!(!is_czi(&bogus));
+        assert!(!is_czi(&[0; 64]));
+        assert!(!is_czi(&[]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_roi_is_a_copy() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let bitmap = czi.read_sub_block(0)?.create_bitmap()?.lock()?;
+        let data = bitmap.lock_info.get_data_roi();
+        assert_eq!(data.len(), bitmap.lock_info.data_len());
+        assert_eq!(data[1], fixtures::tiny_czi_pixel(0, 1, 0));
+        drop(data);
+        // the pixel data is still owned by the bitmap, so it can be read again before the bitmap is unlocked
+        assert_eq!(
+            bitmap.lock_info.get_data_roi()[1],
+            fixtures::tiny_czi_pixel(0, 1, 0)
+        );
+        drop(bitmap);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sub_block_raw_data() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let sub_block = czi.read_sub_block(1)?;
+        let (size, data) = sub_block.get_raw_data(RawDataType::Data, 0)?;
+        assert_eq!(size, fixtures::TINY_CZI_SIZE * fixtures::TINY_CZI_SIZE);
+        assert!(data.is_empty());
+        let (size, data) = sub_block.get_raw_data(RawDataType::Data, size)?;
+        assert_eq!(data.len(), size as usize);
+        assert_eq!(data[33], fixtures::tiny_czi_pixel(1, 1, 1));
+        let (_, data) = sub_block.get_raw_data(RawDataType::Data, 10)?;
+        assert_eq!(data.len(), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unlock() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let bitmap = czi.read_sub_block(0)?.create_bitmap()?.lock()?.unlock()?;
+        let locked = bitmap.lock()?;
+        assert_eq!(
+            locked.lock_info.get_data_roi()[1],
+            fixtures::tiny_czi_pixel(0, 1, 0)
+        );
+        drop(locked.unlock()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_locked_bitmap_rows() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let bitmap = czi
+            .read_crop(
+                Coordinate::new(0b10, [1, 0, 0, 0, 0, 0, 0, 0, 0]),
+                IntRect::new(2, 3, 5, 4),
+            )?
+            .lock()?;
+        assert_eq!(bitmap.data().len(), bitmap.lock_info.data_len());
+        let rows = bitmap.rows()?.collect::<Vec<_>>();
+        assert_eq!(rows.len(), 4);
+        let first = (2..7)
+            .map(|x| fixtures::tiny_czi_pixel(1, x, 3))
+            .collect::<Vec<_>>();
+        assert_eq!(rows[0], first.as_slice());
+        assert!(rows.iter().all(|row| row.len() == 5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_from_threads() -> Result<()> {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<CziReader>();
+        assert_send_sync::<CziWriter>();
+        assert_send_sync::<InputStream>();
+        assert_send_sync::<OutputStream>();
+        assert_send_sync::<Bitmap>();
+
+        let czi = open_tiny_czi()?;
+        std::thread::scope(|scope| {
+            let threads = (0..4)
+                .map(|i| {
+                    let czi = &czi;
+                    scope.spawn(move || -> Result<()> {
+                        for _ in 0..25 {
+                            let c = i % fixtures::TINY_CZI_CHANNELS;
+                            let bitmap = czi.read_sub_block(c)?.create_bitmap()?.lock()?;
+                            assert_eq!(
+                                bitmap.lock_info.get_data_roi()[1],
+                                fixtures::tiny_czi_pixel(c, 1, 0)
+                            );
+                        }
+                        Ok(())
+                    })
+                })
+                .collect::<Vec<_>>();
+            threads
+                .into_iter()
+                .try_for_each(|thread| thread.join().expect("reader thread panicked"))
+        })
+    }
+
+    #[test]
+    fn test_open_from_path() -> Result<()> {
+        let path = fixtures::temp_file_path();
+        assert!(CziReader::open_from_path(&path).is_err());
+        fs::write(&path, fixtures::tiny_czi()?)?;
+        let czi = CziReader::open_from_path(&path)?;
+        fs::remove_file(&path).ok();
+        assert_eq!(czi.sub_block_count()?, fixtures::TINY_CZI_CHANNELS);
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_streams_repeatedly() -> Result<()> {
+        // the file name is only borrowed for the call, run under valgrind to check for leaks
+        let path = fixtures::temp_file_path();
+        fs::write(&path, fixtures::tiny_czi()?)?;
+        let file_name = path.to_str().ok_or(Error::msg("cannot into str"))?;
+        for _ in 0..5000 {
+            InputStream::create_from_file_utf8(file_name)?;
+        }
+        fs::remove_file(&path).ok();
+        Ok(())
+    }
+
+    #[test]
+    fn test_accessor_options_additional_parameters() -> Result<()> {
+        let mut options = AccessorOptions::new(0.0, 0.0, 0.0, true, false, "{}")?;
+        let clone = options.clone();
+        options.set_additional_parameters("{\"a\": 1}")?;
+        assert_eq!(options.get_additional_parameters()?, "{\"a\": 1}");
+        drop(options);
+        assert_eq!(clone.get_additional_parameters()?, "{}");
+        assert_eq!(AccessorOptions::default().get_additional_parameters()?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_accessor_with_additional_parameters() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let accessor = czi.create_single_channel_tile_accessor()?;
+        let options = AccessorOptions::new(0.0, 0.0, 0.0, true, false, "{}")?;
+        let size = fixtures::TINY_CZI_SIZE;
+        accessor.get(
+            Coordinate::new(0b10, [0; 9]),
+            IntRect::new(0, 0, size, size),
+            1.0,
+            options.clone(),
+        )?;
+        assert_eq!(options.get_additional_parameters()?, "{}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_from_memory() -> Result<()> {
+        let bytes = fixtures::tiny_czi()?;
+        let czi = CziReader::open_from_memory(&bytes)?;
+        drop(bytes);
+        assert_eq!(czi.sub_block_count()?, fixtures::TINY_CZI_CHANNELS);
+        let bitmap = czi.read_sub_block(1)?.create_bitmap()?.lock()?;
+        assert_eq!(
+            bitmap.rows()?.nth(2).expect("row 2")[3],
+            fixtures::tiny_czi_pixel(1, 3, 2)
+        );
+        assert!(CziReader::open_from_memory(b"ZISRAWFILE").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_input_stream_from_reader() -> Result<()> {
+        let stream = InputStream::from_reader(io::Cursor::new(fixtures::tiny_czi()?))?;
         let czi = CziReader::create()?;
-        let stream = InputStream::create_from_file_utf8(
-            path.to_str().ok_or(Error::msg("cannot into str"))?,
+        czi.open(ReaderOpenInfo::new(&stream))?;
+        drop(stream);
+        assert_eq!(czi.sub_block_count()?, fixtures::TINY_CZI_CHANNELS);
+        let bitmap = czi.read_sub_block_as_bitmap(1)?;
+        assert_eq!(bitmap.data()[3], fixtures::tiny_czi_pixel(1, 3, 0));
+
+        struct FailingReader;
+
+        impl io::Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("read failed"))
+            }
+        }
+
+        impl io::Seek for FailingReader {
+            fn seek(&mut self, _: io::SeekFrom) -> io::Result<u64> {
+                Ok(0)
+            }
+        }
+
+        let stream = InputStream::from_reader(FailingReader)?;
+        assert!(
+            CziReader::create()?
+                .open(ReaderOpenInfo::new(&stream))
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_stream_from_writer() -> Result<()> {
+        /// a cursor which stays accessible after the output stream drops its clone
+        #[derive(Clone, Default)]
+        struct SharedCursor(Arc<Mutex<io::Cursor<Vec<u8>>>>);
+
+        impl io::Write for SharedCursor {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                io::Write::write(&mut *self.0.lock().unwrap(), buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        impl io::Seek for SharedCursor {
+            fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+                io::Seek::seek(&mut *self.0.lock().unwrap(), pos)
+            }
+        }
+
+        let cursor = SharedCursor::default();
+        fixtures::write_tiny_czi_to(
+            &OutputStream::from_writer(cursor.clone())?,
+            &WriterInitOptions::default(),
         )?;
-        let open_info = ReaderOpenInfo::new(&stream);
-        czi.open(open_info)?;
+        let bytes = cursor.0.lock().unwrap().get_ref().clone();
+        assert!(is_czi(&bytes));
+        let czi = CziReader::open_from_memory(&bytes)?;
+        assert_eq!(czi.sub_block_count()?, fixtures::TINY_CZI_CHANNELS);
+        Ok(())
+    }
+
+    #[test]
+    fn test_input_stream_from_memory() -> Result<()> {
+        let data: Arc<[u8]> = Arc::from(fixtures::tiny_czi()?);
+        let stream = InputStream::create_from_memory(data.clone())?;
+        let czi = CziReader::create()?;
+        czi.open(ReaderOpenInfo::new(&stream))?;
+        drop(stream);
+        assert_eq!(czi.sub_block_count()?, fixtures::TINY_CZI_CHANNELS);
+        assert!(Arc::strong_count(&data) > 1);
+        drop(czi);
+        assert_eq!(Arc::strong_count(&data), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_stream_in_memory() -> Result<()> {
+        let (stream, data) = OutputStream::create_in_memory()?;
+        fixtures::write_tiny_czi_to(&stream, &WriterInitOptions::default())?;
+        drop(stream);
+        let data: Arc<[u8]> = Arc::from(data.lock().unwrap().as_slice());
+        assert!(data.starts_with(b"ZISRAWFILE"));
+        assert!(is_czi(&data));
+        let czi = CziReader::create()?;
+        czi.open(ReaderOpenInfo::new(&InputStream::create_from_memory(data)?))?;
+        assert_eq!(czi.sub_block_count()?, fixtures::TINY_CZI_CHANNELS);
+        assert_eq!(
+            czi.time_stamps()?,
+            Some(fixtures::TINY_CZI_TIME_STAMPS.to_vec())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_time_stamps() -> Result<()> {
+        let mut data = [24u32.to_le_bytes(), 2u32.to_le_bytes()].concat();
+        data.extend([0.5f64, 2.0].iter().flat_map(|t| t.to_le_bytes()));
+        assert_eq!(parse_time_stamps(&data)?, [0.5, 2.0]);
+        assert!(parse_time_stamps(&data[..20]).is_err());
+        assert!(parse_time_stamps(&data[..6]).is_err());
+        // a negative count in the file is a huge count as u32, which must not overflow or panic
+        data[4..8].copy_from_slice(&(-1i32).to_le_bytes());
+        assert!(parse_time_stamps(&data).is_err());
+        data[4..8].copy_from_slice(&0u32.to_le_bytes());
+        assert!(parse_time_stamps(&data)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_stream_flush_error() -> Result<()> {
+        struct FailingFlush(io::Cursor<Vec<u8>>);
+
+        impl io::Write for FailingFlush {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Err(io::Error::other("flush failed"))
+            }
+        }
+
+        impl io::Seek for FailingFlush {
+            fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        let stream = OutputStream::from_writer(FailingFlush(io::Cursor::new(Vec::new())))?;
+        assert!(fixtures::write_tiny_czi_to(&stream, &WriterInitOptions::default()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_stream_from_buf_writer() -> Result<()> {
+        // with a fixed file-GUID, the writer produces the same bytes for the same content
+        let options = WriterInitOptions {
+            file_guid: Some("123e4567-e89b-12d3-a456-426614174000".to_string()),
+            ..Default::default()
+        };
+        let path = fixtures::temp_file_path();
+        let file = io::BufWriter::new(fs::File::create(&path)?);
+        fixtures::write_tiny_czi_to(&OutputStream::from_writer(file)?, &options)?;
+        let direct_path = fixtures::temp_file_path();
+        let output_stream = OutputStream::create_for_file_utf8(
+            direct_path.to_str().ok_or(Error::msg("cannot into str"))?,
+            true,
+        )?;
+        fixtures::write_tiny_czi_to(&output_stream, &options)?;
+        drop(output_stream);
+        let bytes = fs::read(&path)?;
+        let direct_bytes = fs::read(&direct_path)?;
+        fs::remove_file(&path).ok();
+        fs::remove_file(&direct_path).ok();
+        assert!(is_czi(&bytes));
+        assert_eq!(bytes, direct_bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_locked_bitmap_copy() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let bitmap = czi.read_sub_block(1)?.create_bitmap()?.lock()?;
+        let size = fixtures::TINY_CZI_SIZE as u32;
+        let stride = size + 8;
+        let mut data = vec![0; (stride * size) as usize];
+        bitmap.copy(size, size, PixelType::Gray8, stride, &mut data)?;
+        for (x, y) in [(0, 0), (5, 1), (31, 31)] {
+            assert_eq!(
+                data[(y * stride + x) as usize],
+                fixtures::tiny_czi_pixel(1, x as i32, y as i32)
+            );
+        }
+        assert!(
+            bitmap
+                .copy(size, size, PixelType::Gray8, stride, &mut data[..100])
+                .is_err()
+        );
+        assert!(
+            bitmap
+                .copy(size, size, PixelType::Gray8, size - 1, &mut data)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dimension_ranges() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::stack_czi(3, 2, 1)?)?;
+        let ranges = czi.get_all_dimension_ranges()?;
+        assert_eq!(ranges.get(&Dimension::Z), Some(&(0, 2)));
+        assert_eq!(ranges.get(&Dimension::C), Some(&(0, 1)));
+        assert_eq!(ranges.get(&Dimension::T), Some(&(0, 0)));
+        assert_eq!(ranges.get(&Dimension::S), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pixel_type_layout() -> Result<()> {
+        // (raw pixel type, bytes per pixel, channels, floating point, complex) as documented by libCZI
+        let layouts = [
+            (0, 1, 1, false, false),
+            (1, 2, 1, false, false),
+            (2, 4, 1, true, false),
+            (3, 3, 3, false, false),
+            (4, 6, 3, false, false),
+            (8, 12, 3, true, false),
+            (9, 4, 4, false, false),
+            (10, 8, 1, true, true),
+            (11, 24, 3, true, true),
+            (12, 4, 1, false, false),
+            (13, 8, 1, true, false),
+        ];
+        for (raw, bytes_per_pixel, channel_count, is_floating_point, is_complex) in layouts {
+            let pixel_type = PixelType::try_from(raw)?;
+            assert_eq!(pixel_type.bytes_per_pixel(), bytes_per_pixel);
+            assert_eq!(pixel_type.channel_count(), channel_count);
+            assert_eq!(pixel_type.is_floating_point(), is_floating_point);
+            assert_eq!(pixel_type.is_complex(), is_complex);
+            assert_eq!(pixel_type.is_grayscale(), channel_count == 1);
+            assert_eq!(pixel_type.is_color(), channel_count > 1);
+        }
+        // only the raw values listed above are pixel types, so a new pixel type cannot be missed in the table
+        for raw in -1..32 {
+            assert_eq!(
+                PixelType::try_from(raw).is_ok(),
+                layouts.iter().any(|layout| layout.0 == raw)
+            );
+        }
+        // usable in constants
+        const BGR48_CHANNEL_BYTES: usize =
+            PixelType::Bgr48.bytes_per_pixel() / PixelType::Bgr48.channel_count();
+        assert_eq!(BGR48_CHANNEL_BYTES, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pixel_type_name() -> Result<()> {
+        for pixel_type in PixelType::ALL {
+            assert_eq!(
+                pixel_type.name().parse::<PixelType>()? as i32,
+                pixel_type.clone() as i32
+            );
+            assert_eq!(
+                pixel_type.name().to_lowercase().parse::<PixelType>()? as i32,
+                pixel_type as i32
+            );
+        }
+        assert!(matches!("BGR24".parse::<PixelType>()?, PixelType::Bgr24));
+        assert!("Gray12".parse::<PixelType>().is_err());
+        // the pixel type in the metadata of the tiny CZI
+        let xml = open_tiny_czi()?.get_metadata_xml_string()?;
+        let name = xml
+            .split("<PixelType>")
+            .nth(1)
+            .and_then(|s| s.split("</PixelType>").next())
+            .ok_or(Error::msg("no pixel type in metadata"))?;
+        assert!(matches!(name.parse::<PixelType>()?, PixelType::Gray8));
+        Ok(())
+    }
+
+    #[test]
+    fn test_scene_indices() -> Result<()> {
+        assert_eq!(open_tiny_czi()?.get_scene_indices()?, [0]);
+        let czi = CziReader::open_from_memory(&fixtures::pyramid_czi()?)?;
+        assert_eq!(czi.get_scene_indices()?, [0]);
+        let czi = CziReader::open_from_memory(&fixtures::multi_scene_czi()?)?;
+        assert_eq!(
+            czi.get_scene_indices()?,
+            (0..fixtures::MULTI_SCENE_CZI_SCENES).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dimension_char() -> Result<()> {
+        for dimension in Dimension::vec_from_bitflags(0x1ff) {
+            let c = dimension.as_char();
+            assert_eq!(Dimension::try_from(c)?, dimension);
+            assert_eq!(Dimension::try_from(c.to_ascii_lowercase())?, dimension);
+        }
+        assert_eq!(Dimension::S.as_char(), 'S');
+        assert!(Dimension::try_from('X').is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dimension_from_str() -> Result<()> {
+        let dimensions = Dimension::all().collect::<Vec<_>>();
+        assert_eq!(dimensions, Dimension::vec_from_bitflags(0x1ff));
+        assert_eq!(dimensions.last(), Some(&Dimension::B));
+        for dimension in dimensions {
+            assert_eq!(
+                dimension.as_char().to_string().parse::<Dimension>()?,
+                dimension
+            );
+        }
+        assert_eq!("channel".parse::<Dimension>()?, Dimension::C);
+        assert_eq!("Scene".parse::<Dimension>()?, Dimension::S);
+        assert_eq!("BLOCK".parse::<Dimension>()?, Dimension::B);
+        assert_eq!("z".parse::<Dimension>()?, Dimension::Z);
+        assert!("".parse::<Dimension>().is_err());
+        assert!("X".parse::<Dimension>().is_err());
+        assert!("zc".parse::<Dimension>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dimension_ord() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let ranges = czi
+            .get_all_dimension_ranges()?
+            .into_iter()
+            .collect::<std::collections::BTreeMap<_, _>>();
+        assert_eq!(ranges.keys().next(), Some(&Dimension::C));
+        let mut dimensions = Dimension::all().collect::<Vec<_>>();
+        dimensions.reverse();
+        dimensions.sort();
+        assert_eq!(dimensions, Dimension::all().collect::<Vec<_>>());
+        assert!(Dimension::Z < Dimension::B);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dim_bounds() {
+        let mut start = [0; 9];
+        let mut size = [0; 9];
+        start[..2].copy_from_slice(&[0, 2]);
+        size[..2].copy_from_slice(&[5, 6]);
+        let dim_bounds = DimBounds::new(0b11, start, size);
+        assert_eq!(
+            dim_bounds.iter().collect::<Vec<_>>(),
+            [(Dimension::Z, 0, 5), (Dimension::C, 2, 6)]
+        );
+        assert_eq!(dim_bounds.get(Dimension::Z), Some((0, 5)));
+        assert_eq!(dim_bounds.get(Dimension::C), Some((2, 6)));
+        assert_eq!(dim_bounds.get(Dimension::T), None);
+        let dim_bounds = DimBounds::new(0b101, start, size);
+        assert_eq!(dim_bounds.get(Dimension::T), Some((2, 6)));
+        assert_eq!(dim_bounds.get(Dimension::C), None);
+    }
+
+    #[test]
+    fn test_statistics_ex_all() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::multi_scene_czi()?)?;
+        let scenes = fixtures::MULTI_SCENE_CZI_SCENES;
+        let (_, available) = czi.get_statistics_ex(0)?;
+        assert_eq!(available, scenes);
+        let (statistics, available) = czi.get_statistics_ex(scenes - 1)?;
+        assert_eq!(available, scenes);
+        assert_eq!(
+            statistics.get_per_scenes_bounding_boxes().len(),
+            (scenes - 1) as usize
+        );
+        for capacity in [0, scenes - 1, scenes] {
+            let statistics = czi.statistics_ex_all_with_capacity(capacity)?;
+            assert_eq!(statistics.get_number_of_per_scenes_bounding_boxes(), scenes);
+            assert_eq!(statistics.get_sub_block_count(), scenes);
+            let mut scene_indices = statistics
+                .get_per_scenes_bounding_boxes()
+                .iter()
+                .map(|bounding_boxes| bounding_boxes.get_scene_index())
+                .collect::<Vec<_>>();
+            scene_indices.sort();
+            assert_eq!(scene_indices, (0..scenes).collect::<Vec<_>>());
+        }
+        assert_eq!(
+            czi.get_statistics_ex_all()?
+                .get_per_scenes_bounding_boxes()
+                .len(),
+            scenes as usize
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_per_scenes_bounding_boxes() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::multi_scene_czi()?)?;
+        let statistics = czi.get_statistics_ex_all()?.clone();
+        assert_eq!(
+            statistics.get_per_scenes_bounding_boxes().len(),
+            fixtures::MULTI_SCENE_CZI_SCENES as usize
+        );
+        for bounding_boxes in statistics.get_per_scenes_bounding_boxes() {
+            let s = bounding_boxes.get_scene_index();
+            let bounding_box = bounding_boxes.get_bounding_box_layer0_only();
+            assert_eq!(bounding_box.x, 2 * fixtures::TINY_CZI_SIZE * s);
+            assert_eq!(bounding_box.w, fixtures::TINY_CZI_SIZE);
+        }
+        let simple = statistics.get_statistics_simple();
+        let expected = czi.get_statistics_simple()?;
+        assert_eq!(simple.get_sub_block_count(), expected.get_sub_block_count());
+        assert_eq!(
+            simple.get_dim_bounds().get(Dimension::S),
+            expected.get_dim_bounds().get(Dimension::S)
+        );
+        assert_eq!(
+            simple.get_bounding_box().get_w(),
+            expected.get_bounding_box().get_w()
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn test_as_typed_slice() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::mixed_pixel_type_czi()?)?;
+        let index = (0..czi.sub_block_count()?)
+            .find(|&i| {
+                czi.try_get_sub_block_info_for_index(i)
+                    .is_ok_and(|info| matches!(info.get_pixel_type(), Ok(PixelType::Gray16)))
+            })
+            .ok_or(Error::msg("no gray16 sub-block"))?;
+        let bitmap = czi.read_sub_block_as_bitmap(index)?;
+        let data = bitmap.data();
+        let pixels = bitmap
+            .as_typed_slice::<u16>()
+            .ok_or(Error::msg("cannot cast to u16"))?;
+        assert_eq!(pixels.len(), data.len() / 2);
+        assert_eq!(pixels[1], u16::from_le_bytes([data[2], data[3]]));
+        assert_eq!(pixels[1], 64);
+        Ok(())
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_to_dynamic_image() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::mixed_pixel_type_czi()?)?;
+        for index in 0..czi.sub_block_count()? {
+            let bitmap = czi.read_sub_block_as_bitmap(index)?;
+            let info = bitmap.get_info()?;
+            let image = bitmap.to_dynamic_image()?;
+            assert_eq!(image.width(), info.get_width());
+            assert_eq!(image.height(), info.get_height());
+            match info.get_pixel_type()? {
+                PixelType::Gray16 => {
+                    let image = image.as_luma16().ok_or(Error::msg("not luma16"))?;
+                    assert_eq!(image.get_pixel(1, 0).0, [64]);
+                }
+                PixelType::Bgr24 => {
+                    let image = image.as_rgb8().ok_or(Error::msg("not rgb8"))?;
+                    let [b, g, r] = fixtures::MIXED_PIXEL_TYPE_CZI_BGR;
+                    assert_eq!(image.get_pixel(0, 0).0, [r, g, b]);
+                }
+                pixel_type => panic!("unexpected pixel type {:?}", pixel_type),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_locked_bitmap_row() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let data = czi.read_sub_block_as_bitmap(0)?.unlock()?.to_vec()?;
+        let bitmap = czi.read_sub_block_as_bitmap(0)?;
+        let info = bitmap.get_info()?;
+        let width = info.get_width() as usize;
+        for y in [0, 1, info.get_height() - 1] {
+            let row = bitmap.row(y)?;
+            assert_eq!(row, &data[y as usize * width..(y as usize + 1) * width]);
+        }
+        assert!(bitmap.row(info.get_height()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_locked_bitmap_copy_region() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let bitmap = czi.read_sub_block_as_bitmap(0)?;
+        let region = bitmap.copy_region(IntRect::new(5, 7, 10, 10))?;
+        assert_eq!(region.len(), 100);
+        for (x, y) in [(0, 0), (9, 0), (0, 9), (9, 9)] {
+            assert_eq!(region[10 * y + x], bitmap.row(7 + y as u32)?[5 + x]);
+        }
+        assert!(bitmap.copy_region(IntRect::new(30, 0, 10, 10)).is_err());
+        assert!(bitmap.copy_region(IntRect::new(-1, 0, 10, 10)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_locked_bitmap_iter_rows() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let bitmap = czi.read_sub_block_as_bitmap(1)?;
+        let info = bitmap.get_info()?;
+        let stride = bitmap.lock_info.get_stride() as usize;
+        let data = bitmap.lock_info.get_data_roi();
+        let rows = bitmap.iter_rows()?;
+        assert_eq!(rows.len(), info.get_height() as usize);
+        for (y, row) in rows.enumerate() {
+            assert_eq!(row.len(), info.get_width() as usize);
+            assert_eq!(row, &data[y * stride..y * stride + row.len()]);
+        }
+        let last = bitmap.iter_rows()?.next_back().expect("last row");
+        assert_eq!(last, bitmap.row(info.get_height() - 1)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sub_block_pixel_data() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::mixed_pixel_type_czi()?)?;
+        for index in 0..czi.sub_block_count()? {
+            let (info, data) = czi.read_sub_block(index)?.pixel_data()?;
+            assert_eq!(
+                data.len(),
+                (info.get_width() * info.get_height()) as usize
+                    * info.get_pixel_type()?.bytes_per_pixel()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_multi_file() -> Result<()> {
+        let mut bytes = fixtures::tiny_czi()?;
+        assert!(!is_multi_file(&bytes));
+        assert!(!is_multi_file(&bytes[..80]));
+        bytes[48] ^= 1;
+        assert!(is_multi_file(&bytes));
+        bytes[48] ^= 1;
+        bytes[80..84].copy_from_slice(&1i32.to_le_bytes());
+        assert!(is_multi_file(&bytes));
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_external_data() -> Result<()> {
+        let mut bytes = fixtures::tiny_czi()?;
+        assert!(!CziReader::open_from_memory(&bytes)?.has_external_data()?);
+        bytes[80..84].copy_from_slice(&1i32.to_le_bytes());
+        let error = CziReader::open_from_memory(&bytes)
+            .expect_err("a multi-file document must not be opened");
+        assert!(error.to_string().contains("multi-file"));
+        let path = fixtures::temp_file_path();
+        fs::write(&path, &bytes)?;
+        let result = CziReader::open_from_path(&path);
+        fs::remove_file(&path).ok();
+        assert!(result.is_err_and(|error| error.to_string().contains("multi-file")));
+        let czi = CziReader::create()?;
+        czi.open(ReaderOpenInfo::new(&InputStream::create_from_memory(
+            Arc::from(fixtures::tiny_czi()?),
+        )?))?;
+        assert!(czi.has_external_data().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_decode_bytes() -> Result<()> {
+        let size = fixtures::TINY_CZI_SIZE;
+        let decompressed_size = (size * size) as usize;
+        let mut czi = open_tiny_czi()?;
+        assert_eq!(czi.max_decode_bytes(), None);
+        czi.set_max_decode_bytes(Some(decompressed_size - 1));
+        assert!(czi.read_sub_block_as_bitmap(0).is_err());
+        assert!(czi.read_sub_block(0)?.pixel_data().is_err());
+        let roi = IntRect::new(0, 0, size, size);
+        assert!(
+            czi.read_crop(Coordinate::new(0b10, [0; 9]), roi.clone())
+                .is_err()
+        );
+        czi.set_max_decode_bytes(Some(decompressed_size));
+        czi.read_sub_block_as_bitmap(0)?;
+        czi.read_crop(Coordinate::new(0b10, [0; 9]), roi)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_iter_sub_block_infos() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::pyramid_czi()?)?;
+        let infos = czi.iter_sub_block_infos().collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            infos.len(),
+            czi.get_statistics_simple()?.get_sub_block_count() as usize
+        );
+        for (index, info) in infos.iter().enumerate() {
+            let expected = czi.try_get_sub_block_info_for_index(index as i32)?;
+            assert_eq!(
+                info.get_logical_rect().get_w(),
+                expected.get_logical_rect().get_w()
+            );
+            assert_eq!(
+                info.get_logical_rect().get_x(),
+                expected.get_logical_rect().get_x()
+            );
+        }
+        let tiny = open_tiny_czi()?;
+        assert_eq!(
+            tiny.iter_sub_block_infos().count(),
+            tiny.sub_block_count()? as usize
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_coordinate_builder() {
+        let coordinate = Coordinate::builder()
+            .set(Dimension::C, 2)
+            .set(Dimension::Z, 0)
+            .set(Dimension::S, 1)
+            .build();
+        assert_eq!(coordinate.get_dimensions_valid(), 0b10011);
+        assert_eq!(coordinate.get_value(), [0, 2, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(coordinate.get(Dimension::C), Some(2));
+        assert_eq!(coordinate.get(Dimension::T), None);
+        let empty = Coordinate::builder().build();
+        assert_eq!(empty.get_dimensions_valid(), 0);
+    }
+
+    #[test]
+    fn test_iter_attachments() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::thumbnail_czi()?)?;
+        let attachments = czi.iter_attachments()?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(attachments.len(), czi.get_attachment_count()? as usize);
+        let (info, attachment) = attachments
+            .iter()
+            .find(|(info, _)| info.get_name().ok().as_deref() == Some("Thumbnail"))
+            .ok_or(Error::msg("no thumbnail"))?;
+        assert_eq!(&info.get_content_file_type()[..4], b"JPG\0");
+        assert_eq!(
+            attachment.get_raw_data()?,
+            fixtures::THUMBNAIL_CZI_THUMBNAIL
+        );
+
+        let czi = CziReader::open_from_memory(&fixtures::mixed_pixel_type_czi()?)?;
+        assert_eq!(czi.get_attachment_count()?, 0);
+        assert_eq!(czi.iter_attachments()?.count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sub_blocks() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::stack_czi(2, 2, 3)?)?;
+        let sub_blocks = czi.sub_blocks()?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            sub_blocks.len(),
+            czi.get_statistics_simple()?.get_sub_block_count() as usize
+        );
+        assert_eq!(sub_blocks.len(), 12);
+        for sub_block in &sub_blocks {
+            assert_eq!(
+                sub_block.get_info()?.get_logical_rect().get_w(),
+                fixtures::TINY_CZI_SIZE
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_int_rect_intersection() {
+        let rect = IntRect::new(0, 0, 32, 32);
+        let intersection = rect.intersection(&IntRect::new(16, -8, 32, 16)).unwrap();
+        assert_eq!(
+            (
+                intersection.get_x(),
+                intersection.get_y(),
+                intersection.get_w(),
+                intersection.get_h()
+            ),
+            (16, 0, 16, 8)
+        );
+        assert!(!rect.intersects(&IntRect::new(32, 0, 32, 32)));
+        assert!(!rect.intersects(&IntRect::new(0, 0, 0, 0)));
+    }
+
+    #[test]
+    fn test_find_sub_block_infos_in_rect() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::multi_scene_czi()?)?;
+        // the tiles of scene s cover x = 64 * s to 64 * s + 32
+        let rect = IntRect::new(20, 0, 60, 10);
+        let infos = czi.find_sub_block_infos_in_rect(None, &rect)?;
+        let mut xs = infos
+            .iter()
+            .map(|info| info.get_logical_rect().get_x())
+            .collect::<Vec<_>>();
+        xs.sort();
+        assert_eq!(xs, [0, 64]);
+        assert_eq!(czi.find_sub_block_infos_in_rect(Some(1), &rect)?.len(), 1);
+        assert_eq!(czi.find_sub_block_infos_in_rect(Some(2), &rect)?.len(), 0);
+        let all = IntRect::new(0, 0, 1000, 1000);
+        assert_eq!(
+            czi.find_sub_block_infos_in_rect(None, &all)?.len(),
+            fixtures::MULTI_SCENE_CZI_SCENES as usize
+        );
+        assert!(
+            czi.find_sub_block_infos_in_rect(None, &IntRect::new(32, 0, 32, 32))?
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_attachment_infos() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let names = czi
+            .attachment_infos()?
+            .map(|info| info?.get_name())
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(names, ["TimeStamps"]);
+        let czi = CziReader::open_from_memory(&fixtures::thumbnail_czi()?)?;
+        let mut names = czi
+            .attachments()?
+            .map(|attachment| attachment?.get_info()?.get_name())
+            .collect::<Result<Vec<_>>>()?;
+        names.sort();
+        assert_eq!(names, ["Label", "Thumbnail"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_sub_block_as_bitmap() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let logical_rect = czi.try_get_sub_block_info_for_index(0)?.get_logical_rect();
+        let bitmap = czi.read_sub_block_as_bitmap(0)?;
+        let info = bitmap.get_info()?;
+        assert_eq!(info.get_width() as i32, logical_rect.get_w());
+        assert_eq!(info.get_height() as i32, logical_rect.get_h());
+        assert_eq!(bitmap.data()[1], fixtures::tiny_czi_pixel(0, 1, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_thumbnail_and_label_attachments() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::thumbnail_czi()?)?;
+        let thumbnail = czi
+            .get_thumbnail_attachment()?
+            .ok_or(Error::msg("no thumbnail"))?;
+        assert_eq!(thumbnail.get_raw_data()?, fixtures::THUMBNAIL_CZI_THUMBNAIL);
+        let label = czi.get_label_attachment()?.ok_or(Error::msg("no label"))?;
+        assert!(is_czi(&label.get_raw_data()?));
+        let czi = open_tiny_czi()?;
+        assert!(czi.get_thumbnail_attachment()?.is_none());
+        assert!(czi.get_label_attachment()?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_pyramid() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        assert!(!czi.has_pyramid()?);
+        let czi = CziReader::open_from_memory(&fixtures::pyramid_czi()?)?;
+        assert!(czi.has_pyramid()?);
+        assert_eq!(czi.pyramid_layer_count(0)?, 2);
+        assert_eq!(czi.pyramid_layer_count(1)?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_guid_string() -> Result<()> {
+        let guid = "123e4567-e89b-12d3-a456-426614174000";
+        let options = WriterInitOptions {
+            file_guid: Some(guid.to_string()),
+            ..Default::default()
+        };
+        let bytes = fixtures::write_in_memory(|output_stream| {
+            let writer = CziWriter::create("{}")?.init_with_options(output_stream, &options)?;
+            let mut name = [0; 80];
+            name[0] = b'a';
+            writer.add_attachement(AddAttachmentInfo::new(
+                [
+                    0x67, 0x45, 0x3e, 0x12, 0x9b, 0xe8, 0xb3, 0x12, 0xa4, 0x56, 0x42, 0x66, 0x14,
+                    0x17, 0x40, 0x01,
+                ],
+                *b"DAT\0\0\0\0\0",
+                name,
+                &[0],
+            ))?;
+            writer.close()
+        })?;
+        let czi = CziReader::open_from_memory(&bytes)?;
+        let file_header_info = czi.get_file_header_info()?;
+        assert_eq!(file_header_info.get_guid_string(), guid);
+        let attachment_info = czi.get_attachment_info_from_directory(0)?;
+        assert_eq!(
+            attachment_info.get_guid_string(),
+            "123e4567-e89b-12b3-a456-426614174001"
+        );
+        #[cfg(feature = "uuid")]
+        {
+            assert_eq!(
+                file_header_info.get_guid_uuid(),
+                uuid::Uuid::parse_str(guid)?
+            );
+            assert_eq!(
+                attachment_info.get_guid_uuid().to_string(),
+                attachment_info.get_guid_string()
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_file_type_str() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let info = czi.get_attachment_info_from_directory(0)?;
+        assert_eq!(info.content_file_type_str()?, "CZTIMS");
+        let mut name = [0; 80];
+        name[0] = b'a';
+        let add_attachment_info = AddAttachmentInfo::new([0; 16], *b"JPG\0\0\0\0\0", name, &[]);
+        assert_eq!(add_attachment_info.content_file_type_str()?, "JPG");
+        let add_attachment_info = AddAttachmentInfo::new([0; 16], *b"ABCDEFGH", name, &[]);
+        assert_eq!(add_attachment_info.content_file_type_str()?, "ABCDEFGH");
+        Ok(())
+    }
+
+    #[test]
+    fn test_enumerate_stream_classes() -> Result<()> {
+        let stream_classes = enumerate_stream_classes()?;
+        assert!(!stream_classes.is_empty());
+        assert_eq!(stream_classes.len() as i32, stream_classes_count()?);
+        assert!(stream_classes.iter().any(|stream_class| {
+            stream_class
+                .get_name()
+                .is_ok_and(|name| name.to_lowercase().contains("file"))
+        }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_bitmap_to_vec() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let bitmap = czi.read_sub_block(1)?.create_bitmap()?;
+        let data = bitmap.to_vec()?;
+        assert_eq!(
+            data.len(),
+            (fixtures::TINY_CZI_SIZE * fixtures::TINY_CZI_SIZE) as usize
+        );
+        assert_eq!(
+            data[..4],
+            (0..4)
+                .map(|x| fixtures::tiny_czi_pixel(1, x, 0))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            data[fixtures::TINY_CZI_SIZE as usize],
+            fixtures::tiny_czi_pixel(1, 0, 1)
+        );
+        // the bitmap is unlocked again, so it can be locked
+        assert_eq!(bitmap.lock()?.data()[..4], data[..4]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_contains() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        for c in 0..fixtures::TINY_CZI_CHANNELS {
+            assert!(czi.contains(&Coordinate::new(0b10, [c, 0, 0, 0, 0, 0, 0, 0, 0]))?);
+        }
+        let c = fixtures::TINY_CZI_CHANNELS;
+        assert!(!czi.contains(&Coordinate::new(0b10, [c, 0, 0, 0, 0, 0, 0, 0, 0]))?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sub_block_at_point() -> Result<()> {
+        let rect = IntRect::new(10, 20, 5, 5);
+        assert!(rect.contains(10, 20));
+        assert!(rect.contains(14, 24));
+        assert!(!rect.contains(15, 20));
+        assert!(!rect.contains(10, 19));
+
+        let czi = open_tiny_czi()?;
+        let coordinate = Coordinate::new(0b10, [1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let index = czi.sub_block_at_point(&coordinate, 5, 5)?.unwrap();
+        let info = czi.try_get_sub_block_info_for_index(index)?;
+        assert_eq!(info.get_coordinate().get(Dimension::C), Some(1));
+        assert_eq!(
+            czi.sub_block_at_point(&coordinate, fixtures::TINY_CZI_SIZE, 0)?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_crop() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let coordinate = Coordinate::new(0b10, [1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let bitmap = czi
+            .read_crop(coordinate, IntRect::new(4, 6, 8, 8))?
+            .lock()?;
+        let info = bitmap.get_info()?;
+        assert_eq!((info.get_width(), info.get_height()), (8, 8));
+        let stride = bitmap.lock_info.get_stride() as usize;
+        let data = bitmap.lock_info.get_data_roi();
+        for y in 0..8 {
+            for x in 0..8 {
+                assert_eq!(
+                    data[y as usize * stride + x as usize],
+                    fixtures::tiny_czi_pixel(1, x + 4, y + 6)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_bitmap_limited() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let sub_block = czi.read_sub_block(0)?;
+        let size = (fixtures::TINY_CZI_SIZE * fixtures::TINY_CZI_SIZE) as usize;
+        assert!(sub_block.create_bitmap_limited(size - 1).is_err());
+        sub_block.create_bitmap_limited(size)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_pixel_types() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::mixed_pixel_type_czi()?)?;
+        let pixel_types = czi.channel_pixel_types()?;
+        assert_eq!(pixel_types.len(), 2);
+        assert!(matches!(pixel_types[&0], PixelType::Gray16));
+        assert!(matches!(pixel_types[&1], PixelType::Bgr24));
+        Ok(())
+    }
+
+    #[test]
+    fn test_self_test() -> Result<()> {
+        self_test()
+    }
+
+    #[test]
+    fn test_add_sub_block_stores_data_unchanged() -> Result<()> {
+        // the writer does not compress, data marked as compressed is stored as given
+        let data = (0..=255u8).rev().collect::<Vec<_>>();
+        let size = fixtures::TINY_CZI_SIZE;
+        let bytes = fixtures::write_in_memory(|output_stream| {
+            let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+            let mut add_sub_block_info = AddSubBlockInfo::new(
+                Coordinate::new(0b10, [0; 9]),
+                None,
+                0,
+                0,
+                size,
+                size,
+                size,
+                size,
+                PixelType::Gray8,
+                0,
+                &data,
+                &[],
+                &[],
+            );
+            add_sub_block_info.set_compression_mode(CompressionMode::Zstd1);
+            add_sub_block_info.set_stride(size as u32);
+            writer.add_sub_block(add_sub_block_info)?;
+            writer.close()
+        })?;
+        let czi = CziReader::open_from_memory(&bytes)?;
+        let sub_block = czi.read_sub_block(0)?;
+        assert!(matches!(
+            sub_block.get_info()?.get_compression_mode()?,
+            CompressionMode::Zstd1
+        ));
+        let (stored_size, _) = sub_block.get_raw_data(RawDataType::Data, 0)?;
+        assert_eq!(stored_size as usize, data.len());
+        let (_, stored) = sub_block.get_raw_data(RawDataType::Data, stored_size)?;
+        assert_eq!(stored, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_bitmap() -> Result<()> {
+        let source = open_tiny_czi()?;
+        let bitmap = source.read_sub_block(1)?.create_bitmap()?.lock()?;
+        let bytes = fixtures::write_in_memory(|output_stream| {
+            let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+            assert!(
+                writer
+                    .add_bitmap(
+                        Coordinate::new(0b10, [0; 9]),
+                        0,
+                        0,
+                        None,
+                        &bitmap,
+                        CompressionMode::Zstd1
+                    )
+                    .is_err()
+            );
+            writer.add_bitmap(
+                Coordinate::new(0b10, [0; 9]),
+                0,
+                0,
+                None,
+                &bitmap,
+                CompressionMode::UnCompressed,
+            )?;
+            writer.close()
+        })?;
+        let czi = CziReader::open_from_memory(&bytes)?;
+        let copy = czi.read_sub_block(0)?.create_bitmap()?.lock()?;
+        let size = fixtures::TINY_CZI_SIZE;
+        assert_eq!(copy.get_info()?.get_width(), size as u32);
+        let stride = copy.lock_info.get_stride() as usize;
+        let data = copy.lock_info.get_data_roi();
+        assert_eq!(data.len(), copy.lock_info.data_len());
+        for y in 0..size {
+            for x in 0..size {
+                assert_eq!(
+                    data[y as usize * stride + x as usize],
+                    fixtures::tiny_czi_pixel(1, x, y)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_writer_init_options_json() -> Result<()> {
+        let options = WriterInitOptions {
+            file_guid: Some("a\"b".to_string()),
+            reserved_size_attachments_directory: Some(512),
+            ..Default::default()
+        };
+        let json: serde_json::Value = serde_json::from_str(&options.to_json())?;
+        assert_eq!(
+            json,
+            serde_json::json!({"file_guid": "a\"b", "reserved_size_attachments_directory": 512})
+        );
+        assert_eq!(WriterInitOptions::default().to_json(), "{}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_writer_attachment_capacity() -> Result<()> {
+        let options = WriterInitOptions {
+            reserved_size_attachments_directory: Some(256 + 2 * 128),
+            ..Default::default()
+        };
+        assert_eq!(options.attachment_capacity(), Some(2));
+        let bytes = fixtures::write_in_memory(|output_stream| {
+            let writer = CziWriter::create("{}")?.init_with_options(output_stream, &options)?;
+            for i in 0..3u8 {
+                let mut name = [0; 80];
+                name[0] = b'a' + i;
+                let result = writer.add_attachement(AddAttachmentInfo::new(
+                    [i; 16],
+                    *b"DAT\0\0\0\0\0",
+                    name,
+                    &[i],
+                ));
+                assert_eq!(result.is_ok(), i < 2);
+            }
+            writer.close()
+        })?;
+        let czi = CziReader::open_from_memory(&bytes)?;
+        assert_eq!(czi.get_attachment_count()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_attachment_raw_data() -> Result<()> {
+        let content = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+        let bytes = fixtures::write_in_memory(|output_stream| {
+            let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+            let mut name = [0; 80];
+            name[..4].copy_from_slice(b"data");
+            writer.add_attachement(AddAttachmentInfo::new(
+                [1; 16],
+                *b"DAT\0\0\0\0\0",
+                name,
+                &content,
+            ))?;
+            writer.close()
+        })?;
+        let czi = CziReader::open_from_memory(&bytes)?;
+        assert_eq!(czi.read_attachment(0)?.get_raw_data()?, content);
+        Ok(())
+    }
+
+    #[test]
+    fn test_version_report() -> Result<()> {
+        let report = version_report()?;
+        let version = LibCZIVersionInfo::get_lib_czi_version_info()?;
+        assert!(report.starts_with(&format!(
+            "libCZI {}.{}.{} (compiler: ",
+            version.get_major(),
+            version.get_minor(),
+            version.get_patch()
+        )));
+        assert!(report.contains(", tag: "));
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_metadata_from() -> Result<()> {
+        let source = open_tiny_czi()?;
+        let bytes = fixtures::write_in_memory(|output_stream| {
+            let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+            writer.copy_metadata_from_with(&source, |xml| Ok(xml.replace("SizeC>2", "SizeC>1")))?;
+            writer.close()
+        })?;
+        let czi = CziReader::open_from_memory(&bytes)?;
+        let xml = czi.get_metadata_xml_string()?;
+        assert!(xml.contains("<SizeC>1</SizeC>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_shape() -> Result<()> {
+        let czi = open_tiny_czi()?;
         println!("pyramid statistics: {:?}", czi.get_pyramid_statistics()?);
         println!("file header info: {:?}", czi.get_file_header_info()?);
         let statistics_simple = czi.get_statistics_simple()?;
         println!("statistics simple: {:?}", czi.get_statistics_simple()?);
         let bounding_box = statistics_simple.get_bounding_box();
-        let dim_bounds = statistics_simple.get_dim_bounds();
-        let dimensions = Dimension::vec_from_bitflags(dim_bounds.get_dimensions_valid());
-        let size = dim_bounds.get_size();
-        for (i, d) in dimensions.iter().enumerate() {
-            println!("{:?}: {}", d, size[i]);
+        for (d, _, size) in statistics_simple.get_dim_bounds().iter() {
+            println!("{:?}: {}", d, size);
         }
         println!("X: {}", bounding_box.get_w());
         println!("Y: {}", bounding_box.get_h());
@@ -50,16 +1558,7 @@ mod tests {
 
     #[test]
     fn test_read_bytes() -> Result<()> {
-        let path = env::home_dir()
-            .unwrap()
-            .join("code/rust/ndbioimage/tests/files/Experiment-2029.czi");
-        assert!(path.exists());
-        let czi = CziReader::create()?;
-        let stream = InputStream::create_from_file_utf8(
-            path.to_str().ok_or(Error::msg("cannot into str"))?,
-        )?;
-        let open_info = ReaderOpenInfo::new(&stream);
-        czi.open(open_info)?;
+        let czi = open_tiny_czi()?;
         let sub_block = czi.read_sub_block(0)?;
         let bitmap = sub_block.create_bitmap()?.lock()?;
         let bitmap_info = bitmap.get_info()?;
@@ -76,20 +1575,34 @@ mod tests {
 
     #[test]
     fn test_libczi_xml() -> Result<()> {
-        let path = env::home_dir()
-            .unwrap()
-            .join("code/rust/ndbioimage/tests/files/Experiment-2029.czi");
-        assert!(path.exists());
-        let czi = CziReader::create()?;
-        let stream = InputStream::create_from_file_utf8(
-            path.to_str().ok_or(Error::msg("cannot into str"))?,
-        )?;
-        let open_info = ReaderOpenInfo::new(&stream);
-        czi.open(open_info)?;
+        let czi = open_tiny_czi()?;
         let metadata_segment = czi.get_metadata_segment()?;
         let xml = metadata_segment.get_metadata_as_xml()?;
         let s = String::try_from(&xml)?;
         println!("xml: {}", &s[..s.len().min(100)]);
+        assert_eq!(xml.get_data()?, s);
+        drop(xml);
+        assert!(s.contains("<SizeC>2</SizeC>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_metadata_xml_string() -> Result<()> {
+        let xml = open_tiny_czi()?.get_metadata_xml_string()?;
+        assert!(xml.starts_with("<?xml version=\"1.0\""));
+        assert!(xml.contains("<ImageDocument>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_libczi_xml_repeated() -> Result<()> {
+        // every MetadataAsXml frees its own buffer once, run under valgrind to check for leaks
+        let czi = open_tiny_czi()?;
+        let metadata_segment = czi.get_metadata_segment()?;
+        let first = metadata_segment.get_metadata_as_xml()?.get_data()?;
+        for _ in 0..100 {
+            assert_eq!(metadata_segment.get_metadata_as_xml()?.get_data()?, first);
+        }
         Ok(())
     }
 
@@ -97,27 +1610,277 @@ mod tests {
     fn test_libczi_pyramid_statistics() -> Result<()> {
         let path = PathBuf::from("test-files/Experiment-2029.czi");
         assert!(path.exists());
-        let czi = CziReader::create()?;
-        let stream = InputStream::create_from_file_utf8(
-            path.to_str().ok_or(Error::msg("cannot into str"))?,
-        )?;
-        let open_info = ReaderOpenInfo::new(&stream);
-        czi.open(open_info)?;
+        let czi = CziReader::open_from_path(&path)?;
         let s = czi.get_pyramid_statistics()?;
         println!("xml: {}", &s[..s.len().min(100)]);
         Ok(())
     }
 
+    #[test]
+    fn test_base_sub_blocks() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::pyramid_czi()?)?;
+        assert_eq!(czi.sub_block_count()?, 2);
+        let base_sub_blocks = czi.base_sub_blocks()?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(base_sub_blocks.len(), 1);
+        let info = base_sub_blocks[0].get_info()?;
+        assert_eq!(info.get_m_index(), 0);
+        assert_eq!(info.get_physical_size().get_w(), fixtures::TINY_CZI_SIZE);
+        Ok(())
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_save_png() -> Result<()> {
+        let path = PathBuf::from("test-files/Experiment-2029.czi");
+        assert!(path.exists());
+        let czi = CziReader::open_from_path(&path)?;
+        let bitmap = czi.read_sub_block_as_bitmap(0)?;
+        let info = bitmap.get_info()?;
+        let png = fixtures::temp_file_path().with_extension("png");
+        bitmap.save_png(&png)?;
+        let bytes = fs::read(&png)?;
+        fs::remove_file(&png).ok();
+        assert!(!bytes.is_empty());
+        let image = image::load_from_memory(&bytes)?;
+        assert_eq!(image.width(), info.get_width());
+        assert_eq!(image.height(), info.get_height());
+        assert!(image.as_luma16().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_composition_borrows_bitmaps() -> Result<()> {
+        let path = PathBuf::from("test-files/Experiment-2029.czi");
+        assert!(path.exists());
+        let czi = CziReader::open_from_path(&path)?;
+        let bitmaps = vec![czi.read_sub_block(0)?.create_bitmap()?];
+        let channel_info = CompositionChannelInfo::new(1.0, 0, 0, 0, 0, 0.0, 1.0, 0, &[]);
+        let composition = compositor_do_multi_channel_composition(&bitmaps, &[channel_info])?;
+        assert_eq!(
+            composition.get_info()?.get_width(),
+            bitmaps[0].get_info()?.get_width()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_composition_of_two_channels() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let bitmaps = (0..fixtures::TINY_CZI_CHANNELS)
+            .map(|c| czi.read_sub_block(c)?.create_bitmap())
+            .collect::<Result<Vec<_>>>()?;
+        let red = CompositionChannelInfo::new(1.0, 1, 255, 0, 0, 0.0, 1.0, 0, &[]);
+        let green = CompositionChannelInfo::new(1.0, 1, 0, 255, 0, 0.0, 1.0, 0, &[]);
+        let composition = compositor_do_multi_channel_composition(&bitmaps, &[red, green])?;
+        let info = composition.get_info()?;
+        assert_eq!(info.get_width(), fixtures::TINY_CZI_SIZE as u32);
+        assert!(matches!(info.get_pixel_type()?, PixelType::Bgr24));
+        // the pixels of channel 1 are brighter than those of channel 0, so green dominates red
+        let pixel = composition.lock()?.row(1)?[3 * 2..3 * 3].to_vec();
+        assert_eq!(pixel[0], 0);
+        assert!(pixel[1] > pixel[2]);
+        assert!(
+            compositor_do_multi_channel_composition(
+                &bitmaps,
+                &[CompositionChannelInfo::new(
+                    1.0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0.0,
+                    1.0,
+                    0,
+                    &[]
+                )]
+            )
+            .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_composition_channel_info_look_up_table() -> Result<()> {
+        let path = PathBuf::from("test-files/Experiment-2029.czi");
+        assert!(path.exists());
+        let czi = CziReader::open_from_path(&path)?;
+        let display_settings = czi
+            .get_metadata_segment()?
+            .get_czi_document_info()?
+            .get_display_settings()?;
+        for sixteen_bits in [false, true] {
+            let channel_info = display_settings
+                .compositor_fill_out_composition_channel_info_interop(0, sixteen_bits)?;
+            assert_eq!(channel_info.get_tinting_color_g(), 0x99);
+            let look_up_table = channel_info.look_up_table().to_vec();
+            assert_eq!(
+                look_up_table.len(),
+                channel_info.get_look_up_table_element_count() as usize
+            );
+            let clone = channel_info.clone();
+            drop(channel_info);
+            assert_eq!(clone.look_up_table(), look_up_table);
+            assert_eq!(clone.get_look_up_table(), look_up_table);
+        }
+        let mut channel_info = CompositionChannelInfo::new(1.0, 0, 0, 0, 0, 0.0, 1.0, 4, &[1, 2]);
+        assert_eq!(channel_info.look_up_table(), [1, 2, 0, 0]);
+        channel_info.set_look_up_table(&[3; 256]);
+        assert_eq!(channel_info.get_look_up_table_element_count(), 256);
+        channel_info.set_look_up_table_element_count(1);
+        assert_eq!(channel_info.look_up_table(), [3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_settings_channels() -> Result<()> {
+        let path = PathBuf::from("test-files/Experiment-2029.czi");
+        assert!(path.exists());
+        let czi = CziReader::open_from_path(&path)?;
+        let display_settings = czi
+            .get_metadata_segment()?
+            .get_czi_document_info()?
+            .get_display_settings()?;
+        let (_, size_c) = czi
+            .get_statistics_simple()?
+            .get_dim_bounds()
+            .get(Dimension::C)
+            .ok_or(Error::msg("no C dimension"))?;
+        assert_eq!(display_settings.channel_count()?, size_c);
+        let channel_ids = display_settings
+            .channels()?
+            .map(|channel| Ok(channel?.0))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(channel_ids, (0..size_c).collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn test_channel_display_settings_values() -> Result<()> {
+        let path = PathBuf::from("test-files/Experiment-2029.czi");
+        assert!(path.exists());
+        let czi = CziReader::open_from_path(&path)?;
+        let display_settings = czi
+            .get_metadata_segment()?
+            .get_czi_document_info()?
+            .get_display_settings()?;
+        // as in the metadata: Low, High and Color #FF009933 of Channel:0
+        let channel_info =
+            display_settings.compositor_fill_out_composition_channel_info_interop(0, false)?;
+        assert_eq!(channel_info.get_weight(), 1.0);
+        assert_eq!(channel_info.tinting_color(), Some((0x00, 0x99, 0x33)));
+        assert!((channel_info.get_black_point() - 0.000_732_433).abs() < 1e-6);
+        assert!((channel_info.get_white_point() - 0.002_288_853).abs() < 1e-6);
+        assert!(channel_info.is_linear());
+        let mut channel_info = CompositionChannelInfo::new(1.0, 0, 1, 2, 3, 0.0, 1.0, 0, &[]);
+        assert_eq!(channel_info.tinting_color(), None);
+        channel_info.set_enable_tinting(1);
+        assert_eq!(channel_info.tinting_color(), Some((1, 2, 3)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compression_ratio() -> Result<()> {
+        let czi = open_tiny_czi()?;
+        let sub_block = czi.read_sub_block(0)?;
+        assert_eq!(
+            sub_block.get_info()?.decompressed_size()?,
+            (fixtures::TINY_CZI_SIZE * fixtures::TINY_CZI_SIZE) as usize
+        );
+        assert_eq!(sub_block.compression_ratio()?, 1.0);
+
+        let path = PathBuf::from("test-files/Experiment-2029.czi");
+        assert!(path.exists());
+        let czi = CziReader::open_from_path(&path)?;
+        // the sub-blocks of the sample are Zstd1 compressed to about 40% of their size
+        for index in 0..czi.sub_block_count()? {
+            let sub_block = czi.read_sub_block(index)?;
+            assert!(!matches!(
+                sub_block.get_info()?.get_compression_mode()?,
+                CompressionMode::UnCompressed
+            ));
+            let compression_ratio = sub_block.compression_ratio()?;
+            assert!(
+                compression_ratio > 1.0 && compression_ratio < 10.0,
+                "implausible compression ratio {}",
+                compression_ratio
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_compression_ratio_empty_sub_block() -> Result<()> {
+        let size = fixtures::TINY_CZI_SIZE;
+        let bytes = fixtures::write_in_memory(|output_stream| {
+            let writer = CziWriter::create("{}")?.init(output_stream, "{}")?;
+            let mut add_sub_block_info = AddSubBlockInfo::new(
+                Coordinate::new(0b10, [0; 9]),
+                None,
+                0,
+                0,
+                size,
+                size,
+                size,
+                size,
+                PixelType::Gray8,
+                0,
+                &[],
+                &[],
+                &[],
+            );
+            add_sub_block_info.set_compression_mode(CompressionMode::Zstd1);
+            writer.add_sub_block(add_sub_block_info)?;
+            writer.close()
+        })?;
+        let czi = CziReader::open_from_memory(&bytes)?;
+        assert!(czi.read_sub_block(0)?.compression_ratio().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scene_pyramid() -> Result<()> {
+        let statistics: PyramidStatistics = r#"{"scenePyramidStatistics": {"0": [
+            {"layerInfo": {"minificationFactor": 0, "pyramidLayerNo": 0}, "count": 4},
+            {"layerInfo": {"minificationFactor": 2, "pyramidLayerNo": 2}, "count": 1},
+            {"layerInfo": {"minificationFactor": 0, "pyramidLayerNo": 255}, "count": 1}
+        ]}}"#
+            .parse()?;
+        let layers = &statistics.scene_pyramid_statistics[&0];
+        assert_eq!(layers[0].layer_info.zoom(), 1.0);
+        assert_eq!(layers[1].layer_info.zoom(), 0.25);
+        assert!(layers[2].layer_info.is_not_identified());
+
+        let czi = CziReader::open_from_memory(&fixtures::pyramid_czi()?)?;
+        let pyramid = czi.scene_pyramid(0, 0)?;
+        assert_eq!(pyramid.len(), 2);
+        assert_eq!(pyramid[0].0, 1.0);
+        assert_eq!(pyramid[0].1.get_info()?.get_width(), 32);
+        assert_eq!(pyramid[1].0, 0.5);
+        assert_eq!(pyramid[1].1.get_info()?.get_width(), 16);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scenes_with_bitmaps() -> Result<()> {
+        let czi = CziReader::open_from_memory(&fixtures::multi_scene_czi()?)?;
+        let scenes = czi.scenes_with_bitmaps(0, 1.0)?;
+        assert_eq!(scenes.len(), fixtures::MULTI_SCENE_CZI_SCENES as usize);
+        for (s, (scene, bitmap)) in scenes.iter().enumerate() {
+            assert_eq!(*scene, s as i32);
+            let info = bitmap.get_info()?;
+            assert_eq!(info.get_width(), fixtures::TINY_CZI_SIZE as u32);
+            assert_eq!(info.get_height(), fixtures::TINY_CZI_SIZE as u32);
+        }
+        assert_eq!(czi.scenes_with_bitmaps_iter(0, 0.5)?.count(), scenes.len());
+        assert!(czi.scenes_with_bitmaps(1, 1.0)?.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn test_libczi_document_info() -> Result<()> {
         let path = PathBuf::from("test-files/Experiment-2029.czi");
         assert!(path.exists());
-        let czi = CziReader::create()?;
-        let stream = InputStream::create_from_file_utf8(
-            path.to_str().ok_or(Error::msg("cannot into str"))?,
-        )?;
-        let open_info = ReaderOpenInfo::new(&stream);
-        czi.open(open_info)?;
+        let czi = CziReader::open_from_path(&path)?;
         let metadata_segment = czi.get_metadata_segment()?;
         let document_info = metadata_segment.get_czi_document_info()?;
         let general_document_info = document_info.get_general_document_info()?;
@@ -125,6 +1888,24 @@ mod tests {
             "xml: {}",
             &general_document_info[..general_document_info.len().min(100)]
         );
+        for dimension_info in document_info.dimension_infos() {
+            let (dimension, dimension_info) = dimension_info?;
+            println!("{:?}: {:?}", dimension, dimension_info);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_dimension_info_from_str() -> Result<()> {
+        let dimension_info: DimensionInfo =
+            r#"{"start": 0, "end": 4, "unit": "s", "details": {"a": 1}}"#.parse()?;
+        assert_eq!(
+            dimension_info.get("unit").and_then(|u| u.as_str()),
+            Some("s")
+        );
+        assert_eq!(dimension_info.properties.len(), 4);
+        assert!(dimension_info.get("interval").is_none());
+        assert!("".parse::<DimensionInfo>().is_err());
         Ok(())
     }
 
@@ -143,4 +1924,108 @@ mod tests {
         println!("repository tag: {:?}", build_info.get_repository_tag());
         Ok(())
     }
+
+    #[test]
+    fn test_compression_mode() -> Result<()> {
+        for raw in [0, 1, 2, 4, 5, 6] {
+            assert_eq!(CompressionMode::try_from(raw)? as i32, raw);
+        }
+        assert!(CompressionMode::try_from(3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_accessor_fill_value() -> Result<()> {
+        let mut options = AccessorOptions::new(0.0, 0.0, 0.0, false, false, "")?;
+        options.set_fill_value(PixelType::Gray16, 1000.0)?;
+        assert_eq!(
+            options.get_fill_value(PixelType::Gray16)?.map(f64::round),
+            Some(1000.0)
+        );
+        options.set_fill_value(PixelType::Gray32Float, -1.5)?;
+        assert_eq!(options.get_fill_value(PixelType::Gray32Float)?, Some(-1.5));
+        options.set_no_background();
+        assert!(!options.has_background());
+        assert_eq!(options.get_fill_value(PixelType::Gray8)?, None);
+        assert!(
+            options
+                .set_fill_value(PixelType::Gray64ComplexFloat, 0.0)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_stream_error_info() -> Result<()> {
+        let error_info = ExternalStreamErrorInfo::new(1, "read failed")?;
+        assert_eq!(error_info.get_error_code(), 1);
+        assert_eq!(error_info.get_error_message()?, "read failed");
+        let raw = error_info.into_raw();
+        let error_info = ExternalStreamErrorInfo(raw);
+        assert_eq!(error_info.get_error_message()?, "read failed");
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_into_io_error() {
+        for (error, kind) in [
+            (LibCZIApiError::OK, io::ErrorKind::Other),
+            (LibCZIApiError::InvalidArgument, io::ErrorKind::InvalidInput),
+            (LibCZIApiError::InvalidHandle, io::ErrorKind::InvalidInput),
+            (LibCZIApiError::OutOfMemory, io::ErrorKind::OutOfMemory),
+            (LibCZIApiError::IndexOutOfRange, io::ErrorKind::InvalidInput),
+            (
+                LibCZIApiError::LockUnlockSemanticViolated,
+                io::ErrorKind::ResourceBusy,
+            ),
+            (LibCZIApiError::UnspecifiedError, io::ErrorKind::Other),
+        ] {
+            let code = error.code();
+            let io_error = io::Error::from(error);
+            assert_eq!(io_error.kind(), kind);
+            let inner = io_error
+                .get_ref()
+                .and_then(|e| e.downcast_ref::<LibCZIApiError>())
+                .unwrap();
+            assert_eq!(inner.code(), code);
+        }
+    }
+
+    #[test]
+    fn test_downsample_factor() {
+        let mut info = SubBlockInfo::new(
+            0,
+            PixelType::Gray8,
+            Coordinate::new(0, [0; 9]),
+            IntRect::new(0, 0, 1024, 1024),
+            IntSize::new(1024, 1024),
+            0,
+        );
+        assert_eq!(info.downsample_factor(), Some(1.0));
+        assert!(info.is_full_resolution());
+        info.set_physical_size(IntSize::new(256, 256));
+        assert_eq!(info.downsample_factor(), Some(4.0));
+        assert!(!info.is_full_resolution());
+        info.set_physical_size(IntSize::new(0, 0));
+        assert_eq!(info.downsample_factor(), None);
+    }
+
+    #[test]
+    fn test_decompressed_size() -> Result<()> {
+        let mut info = SubBlockInfo::new(
+            0,
+            PixelType::Bgr48,
+            Coordinate::new(0, [0; 9]),
+            IntRect::new(0, 0, 16, 8),
+            IntSize::new(16, 8),
+            0,
+        );
+        assert_eq!(info.decompressed_size()?, 16 * 8 * 6);
+        info.set_physical_size(IntSize::new(-16, 8));
+        assert!(info.decompressed_size().is_err());
+        info.set_physical_size(IntSize::new(i32::MAX, i32::MAX));
+        info.set_pixel_type(PixelType::Bgr192ComplexFloat);
+        assert!(info.decompressed_size().is_err());
+        Ok(())
+    }
 }
@@ -1,7 +1,12 @@
 use anyhow::{Error, Result, anyhow};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::io;
 use std::mem::MaybeUninit;
 use std::os::raw::c_int;
+use std::str::FromStr;
 
 /// the error type for libCZIAPI
 #[derive(Clone, Debug)]
@@ -34,13 +39,43 @@ impl TryFrom<c_int> for LibCZIApiError {
     }
 }
 
+impl LibCZIApiError {
+    /// the numeric error code used by libCZIAPI
+    pub fn code(&self) -> c_int {
+        match self {
+            LibCZIApiError::OK => 0,
+            LibCZIApiError::InvalidArgument => 1,
+            LibCZIApiError::InvalidHandle => 2,
+            LibCZIApiError::OutOfMemory => 3,
+            LibCZIApiError::IndexOutOfRange => 4,
+            LibCZIApiError::LockUnlockSemanticViolated => 20,
+            LibCZIApiError::UnspecifiedError => 50,
+        }
+    }
+}
+
+/// the libCZIAPI error is kept as the inner error of the io::Error, so its code remains available
+impl From<LibCZIApiError> for io::Error {
+    fn from(error: LibCZIApiError) -> Self {
+        let kind = match error {
+            LibCZIApiError::InvalidArgument
+            | LibCZIApiError::InvalidHandle
+            | LibCZIApiError::IndexOutOfRange => io::ErrorKind::InvalidInput,
+            LibCZIApiError::OutOfMemory => io::ErrorKind::OutOfMemory,
+            LibCZIApiError::LockUnlockSemanticViolated => io::ErrorKind::ResourceBusy,
+            LibCZIApiError::OK | LibCZIApiError::UnspecifiedError => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, error)
+    }
+}
+
 impl fmt::Display for LibCZIApiError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "LibCZIApi {self:?}")
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Dimension {
     /// The Z-dimension.
     Z = 1,
@@ -74,6 +109,70 @@ impl Dimension {
         }
         dimensions
     }
+
+    /// the letter by which the dimension is conventionally written, for instance 'Z'
+    pub fn as_char(&self) -> char {
+        match self {
+            Dimension::Z => 'Z',
+            Dimension::C => 'C',
+            Dimension::T => 'T',
+            Dimension::R => 'R',
+            Dimension::S => 'S',
+            Dimension::I => 'I',
+            Dimension::H => 'H',
+            Dimension::V => 'V',
+            Dimension::B => 'B',
+        }
+    }
+
+    /// all dimensions, in the order of their values: Z, C, T, R, S, I, H, V, B
+    pub fn all() -> impl Iterator<Item = Dimension> {
+        (1..=9).map(|i| Dimension::try_from(i).expect("i must be 1 <= i <= 9"))
+    }
+}
+
+/// the dimension written as a letter (see [Dimension::as_char]) or as its name, for instance "channel" for C,
+/// case-insensitive
+impl FromStr for Dimension {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Dimension::try_from(c);
+        }
+        match s.to_ascii_lowercase().as_str() {
+            "channel" => Ok(Dimension::C),
+            "time" => Ok(Dimension::T),
+            "rotation" => Ok(Dimension::R),
+            "scene" => Ok(Dimension::S),
+            "illumination" => Ok(Dimension::I),
+            "phase" => Ok(Dimension::H),
+            "view" => Ok(Dimension::V),
+            "block" => Ok(Dimension::B),
+            _ => Err(anyhow!("Unknown dimension {}", s)),
+        }
+    }
+}
+
+/// the dimension written as a letter, case-insensitive
+impl TryFrom<char> for Dimension {
+    type Error = Error;
+
+    fn try_from(dimension: char) -> Result<Self> {
+        match dimension.to_ascii_uppercase() {
+            'Z' => Ok(Dimension::Z),
+            'C' => Ok(Dimension::C),
+            'T' => Ok(Dimension::T),
+            'R' => Ok(Dimension::R),
+            'S' => Ok(Dimension::S),
+            'I' => Ok(Dimension::I),
+            'H' => Ok(Dimension::H),
+            'V' => Ok(Dimension::V),
+            'B' => Ok(Dimension::B),
+            _ => Err(anyhow!("Unknown dimension {}", dimension)),
+        }
+    }
 }
 
 impl TryFrom<i32> for Dimension {
@@ -151,6 +250,294 @@ impl TryFrom<i32> for PixelType {
     }
 }
 
+impl FromStr for PixelType {
+    type Err = Error;
+
+    /// parse the name of a pixel type as used by libCZI, for example in the metadata, ignoring case
+    fn from_str(s: &str) -> Result<Self> {
+        PixelType::ALL
+            .into_iter()
+            .find(|pixel_type| pixel_type.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| anyhow!("Unknown pixel type {}", s))
+    }
+}
+
+impl PixelType {
+    /// all pixel types
+    pub const ALL: [PixelType; 11] = [
+        PixelType::Gray8,
+        PixelType::Gray16,
+        PixelType::Gray32Float,
+        PixelType::Bgr24,
+        PixelType::Bgr48,
+        PixelType::Bgr96Float,
+        PixelType::Bgra32,
+        PixelType::Gray64ComplexFloat,
+        PixelType::Bgr192ComplexFloat,
+        PixelType::Gray32,
+        PixelType::Gray64Float,
+    ];
+
+    /// the name of the pixel type as used by libCZI, for example in the metadata
+    pub const fn name(&self) -> &'static str {
+        match self {
+            PixelType::Gray8 => "Gray8",
+            PixelType::Gray16 => "Gray16",
+            PixelType::Gray32Float => "Gray32Float",
+            PixelType::Bgr24 => "Bgr24",
+            PixelType::Bgr48 => "Bgr48",
+            PixelType::Bgr96Float => "Bgr96Float",
+            PixelType::Bgra32 => "Bgra32",
+            PixelType::Gray64ComplexFloat => "Gray64ComplexFloat",
+            PixelType::Bgr192ComplexFloat => "Bgr192ComplexFloat",
+            PixelType::Gray32 => "Gray32",
+            PixelType::Gray64Float => "Gray64Float",
+        }
+    }
+
+    /// number of bytes used for one pixel
+    pub const fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PixelType::Gray8 => 1,
+            PixelType::Gray16 => 2,
+            PixelType::Gray32Float => 4,
+            PixelType::Bgr24 => 3,
+            PixelType::Bgr48 => 6,
+            PixelType::Bgr96Float => 12,
+            PixelType::Bgra32 => 4,
+            PixelType::Gray64ComplexFloat => 8,
+            PixelType::Bgr192ComplexFloat => 24,
+            PixelType::Gray32 => 4,
+            PixelType::Gray64Float => 8,
+        }
+    }
+
+    /// number of color channels in one pixel: 1 for gray, 3 for BGR and 4 for BGRA pixel types
+    pub const fn channel_count(&self) -> usize {
+        match self {
+            PixelType::Gray8
+            | PixelType::Gray16
+            | PixelType::Gray32Float
+            | PixelType::Gray64ComplexFloat
+            | PixelType::Gray32
+            | PixelType::Gray64Float => 1,
+            PixelType::Bgr24
+            | PixelType::Bgr48
+            | PixelType::Bgr96Float
+            | PixelType::Bgr192ComplexFloat => 3,
+            PixelType::Bgra32 => 4,
+        }
+    }
+
+    /// whether the channels of a pixel are floating point (or complex floating point) numbers
+    pub const fn is_floating_point(&self) -> bool {
+        matches!(
+            self,
+            PixelType::Gray32Float
+                | PixelType::Bgr96Float
+                | PixelType::Gray64ComplexFloat
+                | PixelType::Bgr192ComplexFloat
+                | PixelType::Gray64Float
+        )
+    }
+
+    /// whether the channels of a pixel are complex floating point numbers
+    pub const fn is_complex(&self) -> bool {
+        matches!(
+            self,
+            PixelType::Gray64ComplexFloat | PixelType::Bgr192ComplexFloat
+        )
+    }
+
+    /// whether a pixel has a single (gray) channel
+    pub const fn is_grayscale(&self) -> bool {
+        self.channel_count() == 1
+    }
+
+    /// whether a pixel has color channels (BGR or BGRA), the opposite of [PixelType::is_grayscale]
+    pub const fn is_color(&self) -> bool {
+        !self.is_grayscale()
+    }
+}
+
+/// identification of a pyramid layer, as in the pyramid statistics of libCZI
+#[derive(Clone, Debug, Deserialize)]
+pub struct PyramidLayerInfo {
+    /// the factor by which each layer is minified with respect to the layer below it, 0 for layer 0
+    #[serde(rename = "minificationFactor")]
+    pub minification_factor: u8,
+    /// the number of the layer, 0 for the layer with the full resolution, 0xff if it could not be determined
+    #[serde(rename = "pyramidLayerNo")]
+    pub pyramid_layer_no: u8,
+}
+
+impl PyramidLayerInfo {
+    /// whether the layer could not be identified as a pyramid layer
+    pub fn is_not_identified(&self) -> bool {
+        self.pyramid_layer_no == 0xff
+    }
+
+    /// the zoom of the layer with respect to layer 0: 1 / minification_factor ^ pyramid_layer_no
+    pub fn zoom(&self) -> f32 {
+        if self.pyramid_layer_no == 0 {
+            1.0
+        } else {
+            1.0 / (self.minification_factor as f32).powi(self.pyramid_layer_no as i32)
+        }
+    }
+}
+
+/// number of sub-blocks in a pyramid layer
+#[derive(Clone, Debug, Deserialize)]
+pub struct PyramidLayerStatistics {
+    #[serde(rename = "layerInfo")]
+    pub layer_info: PyramidLayerInfo,
+    pub count: i32,
+}
+
+/// the pyramid layers present for each scene, parsed from [crate::CziReader::get_pyramid_statistics]
+#[derive(Clone, Debug, Deserialize)]
+pub struct PyramidStatistics {
+    #[serde(rename = "scenePyramidStatistics")]
+    pub scene_pyramid_statistics: BTreeMap<i32, Vec<PyramidLayerStatistics>>,
+}
+
+impl FromStr for PyramidStatistics {
+    type Err = Error;
+
+    fn from_str(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// the information about a dimension in the XML-metadata, parsed from [crate::CziDocumentInfo::get_dimension_info]
+#[derive(Clone, Debug, Deserialize)]
+pub struct DimensionInfo {
+    /// all properties libCZI reports for the dimension, which depend on the dimension
+    #[serde(flatten)]
+    pub properties: BTreeMap<String, serde_json::Value>,
+}
+
+impl DimensionInfo {
+    /// the property with the key, None if libCZI does not report it
+    pub fn get(&self, key: &str) -> Option<&serde_json::Value> {
+        self.properties.get(key)
+    }
+}
+
+impl FromStr for DimensionInfo {
+    type Err = Error;
+
+    fn from_str(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// Options for initializing a writer, see [crate::CziWriter::init_with_options]. Options which are None are not
+/// passed to libCZI, so its defaults apply.
+#[derive(Clone, Debug, Default)]
+pub struct WriterInitOptions {
+    /// the file-GUID of the new document, for instance \"123e4567-e89b-12d3-a456-426614174000\"
+    pub file_guid: Option<String>,
+    /// size in bytes reserved for the attachment directory at the start of the file, this determines how many
+    /// attachments can be added, see [WriterInitOptions::attachment_capacity]
+    pub reserved_size_attachments_directory: Option<u32>,
+    /// size in bytes reserved for the metadata segment at the start of the file
+    pub reserved_size_metadata_segment: Option<u32>,
+    pub minimum_m_index: Option<i32>,
+    pub maximum_m_index: Option<i32>,
+}
+
+impl WriterInitOptions {
+    /// the options as the JSON-formatted string expected by libCZI
+    pub fn to_json(&self) -> String {
+        let options = [
+            ("file_guid", self.file_guid.as_ref().map(|guid| json!(guid))),
+            (
+                "reserved_size_attachments_directory",
+                self.reserved_size_attachments_directory
+                    .map(|size| json!(size)),
+            ),
+            (
+                "reserved_size_metadata_segment",
+                self.reserved_size_metadata_segment.map(|size| json!(size)),
+            ),
+            ("minimum_m_index", self.minimum_m_index.map(|m| json!(m))),
+            ("maximum_m_index", self.maximum_m_index.map(|m| json!(m))),
+        ];
+        let options = options
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::Value::Object(options).to_string()
+    }
+
+    /// The number of attachments fitting in the reserved attachment directory, None if no space is reserved. The
+    /// attachment directory consists of a 256 byte header followed by an entry of 128 bytes per attachment.
+    pub fn attachment_capacity(&self) -> Option<usize> {
+        self.reserved_size_attachments_directory
+            .map(|size| (size as usize).saturating_sub(256) / 128)
+    }
+}
+
+/// Check whether bytes start with the header of a CZI file, without invoking libCZI. A CZI file starts with a
+/// segment header of 32 bytes: the segment id ZISRAWFILE padded with zeros to 16 bytes, followed by the allocated
+/// and used size of the segment as i64. This is a cheap pre-filter, a file passing it may still fail to open.
+pub fn is_czi(bytes: &[u8]) -> bool {
+    const ID: &[u8; 10] = b"ZISRAWFILE";
+    if bytes.len() < 32 || &bytes[..10] != ID || bytes[10..16].iter().any(|b| *b != 0) {
+        return false;
+    }
+    let allocated_size = i64::from_le_bytes(bytes[16..24].try_into().expect("slice of 8 bytes"));
+    let used_size = i64::from_le_bytes(bytes[24..32].try_into().expect("slice of 8 bytes"));
+    (0..=allocated_size).contains(&used_size)
+}
+
+/// number of bytes at the start of a CZI file needed by [is_multi_file]
+pub(crate) const FILE_HEADER_LEN: usize = 84;
+
+/// Check whether bytes start with the header of a CZI file which is part of a multi-file document, without invoking
+/// libCZI. After the segment header (see [is_czi]), the file header holds the major and minor version and two
+/// reserved fields as i32, the PrimaryFileGuid and FileGuid of 16 bytes each, and the FilePart as i32. A file is part
+/// of a multi-file document if its FilePart is not 0 or its PrimaryFileGuid is set and differs from its FileGuid.
+/// libCZI does not support multi-file documents, sub-blocks stored in other parts cannot be read.
+pub fn is_multi_file(bytes: &[u8]) -> bool {
+    if !is_czi(bytes) || bytes.len() < FILE_HEADER_LEN {
+        return false;
+    }
+    let primary_file_guid = &bytes[48..64];
+    let file_guid = &bytes[64..80];
+    let file_part = i32::from_le_bytes(bytes[80..84].try_into().expect("slice of 4 bytes"));
+    file_part != 0 || (primary_file_guid.iter().any(|b| *b != 0) && primary_file_guid != file_guid)
+}
+
+/// compression mode of a sub-block, the discriminant is the raw compression identifier used in CZI
+#[derive(Clone, Debug)]
+pub enum CompressionMode {
+    UnCompressed = 0,
+    Jpg = 1,
+    Lzw = 2,
+    JpgXr = 4,
+    Zstd0 = 5,
+    Zstd1 = 6,
+}
+
+impl TryFrom<i32> for CompressionMode {
+    type Error = Error;
+
+    fn try_from(compression_mode: i32) -> Result<Self> {
+        match compression_mode {
+            0 => Ok(CompressionMode::UnCompressed),
+            1 => Ok(CompressionMode::Jpg),
+            2 => Ok(CompressionMode::Lzw),
+            4 => Ok(CompressionMode::JpgXr),
+            5 => Ok(CompressionMode::Zstd0),
+            6 => Ok(CompressionMode::Zstd1),
+            _ => Err(anyhow!("Unknown compression mode {}", compression_mode)),
+        }
+    }
+}
+
 pub trait Ptr {
     type Pointer;
 